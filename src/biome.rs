@@ -0,0 +1,187 @@
+//local
+use crate::block::{BlockId, BlockRegistry};
+use crate::noise::Perlin;
+
+/// Index into BIOMES, one per world column.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct BiomeId(pub u8);
+
+pub struct BiomeDef {
+    pub name: &'static str,
+    // where the biome sits in climate space, both 0-1
+    pub temperature: f64,
+    pub humidity: f64,
+    // top block and the blocks under it down to `depth`, stone below that
+    pub surface: &'static str,
+    pub subsurface: &'static str,
+    pub depth: i32,
+    // added to the generator's base height, and multiplier on its noise amplitude
+    pub heightOffset: f64,
+    pub heightScale: f64,
+    // feature placement attempts per chunk
+    pub trees: u32,
+    pub boulders: u32,
+}
+
+pub const BIOMES: [BiomeDef; 5] = [
+    BiomeDef {
+        name: "plains",
+        temperature: 0.5,
+        humidity: 0.4,
+        surface: "grass",
+        subsurface: "dirt",
+        depth: 3,
+        heightOffset: 0.0,
+        heightScale: 0.5,
+        trees: 1,
+        boulders: 1,
+    },
+    BiomeDef {
+        name: "forest",
+        temperature: 0.55,
+        humidity: 0.8,
+        surface: "grass",
+        subsurface: "dirt",
+        depth: 4,
+        heightOffset: 4.0,
+        heightScale: 0.8,
+        trees: 10,
+        boulders: 0,
+    },
+    BiomeDef {
+        name: "desert",
+        temperature: 0.9,
+        humidity: 0.1,
+        surface: "sand",
+        subsurface: "sand",
+        depth: 5,
+        heightOffset: -2.0,
+        heightScale: 0.3,
+        trees: 0,
+        boulders: 1,
+    },
+    BiomeDef {
+        name: "tundra",
+        temperature: 0.1,
+        humidity: 0.3,
+        surface: "snow",
+        subsurface: "dirt",
+        depth: 2,
+        heightOffset: 2.0,
+        heightScale: 0.6,
+        trees: 1,
+        boulders: 0,
+    },
+    BiomeDef {
+        name: "mountains",
+        temperature: 0.2,
+        humidity: 0.8,
+        surface: "stone",
+        subsurface: "stone",
+        depth: 1,
+        heightOffset: 18.0,
+        heightScale: 1.8,
+        trees: 0,
+        boulders: 3,
+    },
+];
+
+impl BiomeId {
+    pub fn def(self) -> &'static BiomeDef {
+        &BIOMES[self.0 as usize]
+    }
+}
+
+// BiomeDef with its block names looked up
+#[derive(Copy, Clone)]
+pub struct BiomeBlocks {
+    pub surface: BlockId,
+    pub subsurface: BlockId,
+    pub depth: i32,
+}
+
+pub fn resolveBiomes(registry: &BlockRegistry) -> Vec<BiomeBlocks> {
+    let lookup = |name: &str| {
+        registry
+            .id(name)
+            .unwrap_or_else(|| panic!("biome: no '{}' block defined", name))
+    };
+    BIOMES
+        .iter()
+        .map(|b| BiomeBlocks {
+            surface: lookup(b.surface),
+            subsurface: lookup(b.subsurface),
+            depth: b.depth,
+        })
+        .collect()
+}
+
+/// What the climate says about one column: the dominant biome for surface
+/// rules, plus height settings blended over every biome by climate distance
+/// so borders slope instead of stepping.
+pub struct ColumnClimate {
+    pub biome: BiomeId,
+    pub heightOffset: f64,
+    pub heightScale: f64,
+}
+
+/// Seeded temperature and humidity noise.
+pub struct ClimateMap {
+    temperature: Perlin,
+    humidity: Perlin,
+    // blocks per noise unit
+    scale: f64,
+}
+
+// how far in climate space a biome's influence reaches when blending
+const BLENDWIDTH: f64 = 0.12;
+
+impl ClimateMap {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            temperature: Perlin::new(seed.wrapping_add(1)),
+            humidity: Perlin::new(seed.wrapping_add(2)),
+            scale: 512.0,
+        }
+    }
+
+    // (temperature, humidity) at a world column, both clamped to 0-1
+    pub fn climate(&self, wx: i32, wz: i32) -> (f64, f64) {
+        let (x, z) = (wx as f64 / self.scale, wz as f64 / self.scale);
+        let t = self.temperature.fbm2(x, z, 3) * 1.5 + 0.5;
+        let h = self.humidity.fbm2(x + 31.7, z - 12.3, 3) * 1.5 + 0.5;
+        (t.clamp(0.0, 1.0), h.clamp(0.0, 1.0))
+    }
+
+    pub fn column(&self, wx: i32, wz: i32) -> ColumnClimate {
+        let (t, h) = self.climate(wx, wz);
+
+        let mut best = (0, f64::MAX);
+        let (mut offset, mut scale, mut total) = (0.0, 0.0, 0.0);
+        for (i, b) in BIOMES.iter().enumerate() {
+            let d2 = (b.temperature - t).powi(2) + (b.humidity - h).powi(2);
+            if d2 < best.1 {
+                best = (i, d2);
+            }
+            let w = (-d2 / (BLENDWIDTH * BLENDWIDTH)).exp();
+            offset += w * b.heightOffset;
+            scale += w * b.heightScale;
+            total += w;
+        }
+
+        let biome = BiomeId(best.0 as u8);
+        if total < 1e-9 {
+            // too far from every biome for the weights to matter
+            return ColumnClimate {
+                biome,
+                heightOffset: biome.def().heightOffset,
+                heightScale: biome.def().heightScale,
+            };
+        }
+        ColumnClimate {
+            biome,
+            heightOffset: offset / total,
+            heightScale: scale / total,
+        }
+    }
+}
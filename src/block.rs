@@ -0,0 +1,583 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+//local
+use crate::model::{BlockModel, BoxFace, ModelBox};
+
+// face names in DIRS order, as used by the `textures` table
+pub const FACES: [&str; 6] = ["back", "front", "left", "right", "bottom", "top"];
+
+/// Numeric block type, assigned by the BlockRegistry when the definitions
+/// are loaded. Only `AIR` has a fixed value.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct BlockId(pub u16);
+
+impl BlockId {
+    pub const AIR: BlockId = BlockId(0);
+}
+
+/// Per-block state bits packed into a u16:
+/// bits 0-2 facing (a DIRS index), 3-4 axis (0 y, 1 x, 2 z), 5-8 variant, 9 waterlogged.
+/// The default state faces front (+Z) along the y axis. Fluids keep their
+/// level in the low three variant bits and a falling flag in the top one.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct BlockState(pub u16);
+
+impl BlockState {
+    pub const AXIS_Y: u8 = 0;
+    pub const AXIS_X: u8 = 1;
+    pub const AXIS_Z: u8 = 2;
+
+    const FACING_MASK: u16 = 0b111;
+    const AXIS_SHIFT: u16 = 3;
+    const AXIS_MASK: u16 = 0b11 << BlockState::AXIS_SHIFT;
+    const VARIANT_SHIFT: u16 = 5;
+    const VARIANT_MASK: u16 = 0b1111 << BlockState::VARIANT_SHIFT;
+    const WATERLOGGED: u16 = 1 << 9;
+
+    pub fn facing(self) -> usize {
+        (self.0 & BlockState::FACING_MASK) as usize
+    }
+
+    pub fn withFacing(self, facing: usize) -> Self {
+        assert!(facing < 6, "BlockState::withFacing(): not a DIRS index");
+        BlockState((self.0 & !BlockState::FACING_MASK) | facing as u16)
+    }
+
+    pub fn axis(self) -> u8 {
+        ((self.0 & BlockState::AXIS_MASK) >> BlockState::AXIS_SHIFT) as u8
+    }
+
+    pub fn withAxis(self, axis: u8) -> Self {
+        assert!(
+            axis <= BlockState::AXIS_Z,
+            "BlockState::withAxis(): bad axis"
+        );
+        BlockState((self.0 & !BlockState::AXIS_MASK) | (axis as u16) << BlockState::AXIS_SHIFT)
+    }
+
+    pub fn variant(self) -> u8 {
+        ((self.0 & BlockState::VARIANT_MASK) >> BlockState::VARIANT_SHIFT) as u8
+    }
+
+    pub fn withVariant(self, variant: u8) -> Self {
+        assert!(variant < 16, "BlockState::withVariant(): variant above 15");
+        BlockState(
+            (self.0 & !BlockState::VARIANT_MASK) | (variant as u16) << BlockState::VARIANT_SHIFT,
+        )
+    }
+
+    // 0 is a source block, 1-7 flowing and thinner the higher it gets
+    pub fn level(self) -> u8 {
+        self.variant() & 0b111
+    }
+
+    pub fn withLevel(self, level: u8) -> Self {
+        assert!(level < 8, "BlockState::withLevel(): level above 7");
+        self.withVariant((self.variant() & !0b111) | level)
+    }
+
+    // fluid pouring down from the block above, spreads like a source
+    pub fn falling(self) -> bool {
+        self.variant() & 0b1000 != 0
+    }
+
+    pub fn withFalling(self, falling: bool) -> Self {
+        let v = self.variant() & 0b111;
+        self.withVariant(if falling { v | 0b1000 } else { v })
+    }
+
+    pub fn waterlogged(self) -> bool {
+        self.0 & BlockState::WATERLOGGED != 0
+    }
+
+    pub fn withWaterlogged(self, waterlogged: bool) -> Self {
+        if waterlogged {
+            BlockState(self.0 | BlockState::WATERLOGGED)
+        } else {
+            BlockState(self.0 & !BlockState::WATERLOGGED)
+        }
+    }
+}
+
+impl Default for BlockState {
+    fn default() -> Self {
+        // facing front
+        BlockState(1)
+    }
+}
+
+/// What a chunk actually stores per cell.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Block {
+    pub id: BlockId,
+    pub state: BlockState,
+}
+
+impl Block {
+    pub const AIR: Block = Block {
+        id: BlockId::AIR,
+        state: BlockState(1),
+    };
+
+    pub fn new(id: BlockId, state: BlockState) -> Self {
+        Self { id, state }
+    }
+}
+
+impl From<BlockId> for Block {
+    fn from(id: BlockId) -> Self {
+        Block::new(id, BlockState::default())
+    }
+}
+
+// how a block's state turns its model
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    #[default]
+    None,
+    // follows state.axis(), the top/bottom textures end up on the two faces along the axis (logs)
+    Axis,
+    // follows state.facing(), the front texture ends up on the facing side (furnaces, stairs)
+    Facing,
+}
+
+/// Which pass a block is drawn in, and so what it hides.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderClass {
+    // fully covers its cell, hides every face next to it
+    #[default]
+    Opaque,
+    // texture pixels are either there or not (leaves), drawn without blending
+    Cutout,
+    // blended over what's behind it, sorted back to front (glass, water)
+    Translucent,
+}
+
+// model face shown on each world face, [rotation][world face] in DIRS order
+const AXIS_FACES: [[usize; 6]; 3] = [
+    [0, 1, 2, 3, 4, 5], // y
+    [0, 1, 4, 5, 3, 2], // x
+    [4, 5, 2, 3, 1, 0], // z
+];
+// quarter turns of the uv corners so bark grain follows the axis
+const AXIS_TURNS: [[u8; 6]; 3] = [[0, 0, 0, 0, 0, 0], [1, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 0]];
+const FACING_FACES: [[usize; 6]; 6] = [
+    [1, 0, 3, 2, 4, 5], // back
+    [0, 1, 2, 3, 4, 5], // front
+    [2, 3, 1, 0, 4, 5], // left
+    [3, 2, 0, 1, 4, 5], // right
+    [4, 5, 2, 3, 1, 0], // down
+    [5, 4, 2, 3, 0, 1], // up
+];
+const FACING_TURNS: [[u8; 6]; 6] = [
+    [0, 0, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 3, 1],
+    [0, 0, 0, 0, 1, 3],
+    [0, 0, 1, 3, 0, 0],
+    [0, 0, 3, 1, 0, 0],
+];
+
+pub struct BlockDef {
+    pub name: String,
+    // texture array layer per face, in DIRS order (see FACES)
+    pub layers: [u32; 6],
+    pub render: RenderClass,
+    // opaque full cubes, these hide the faces of anything next to them and shade corners
+    pub opaque: bool,
+    // drawn instead of a cube when set, see model.rs
+    pub model: Option<BlockModel>,
+    pub rotation: Rotation,
+    // set for blocks the generator scatters as veins
+    pub ore: Option<OreDef>,
+    // set for water, lava and the like, see World::tick
+    pub fluid: Option<FluidDef>,
+}
+
+/// How a fluid block flows.
+#[derive(Clone, Debug)]
+pub struct FluidDef {
+    // ticks between a change next to the fluid and it reacting
+    pub tickDelay: u32,
+    // level lost per block of sideways flow, 1 reaches 7 blocks from a source
+    pub levelDrop: u8,
+    // what this fluid turns into when a different fluid touches it
+    pub hardens: Option<BlockId>,
+}
+
+/// Where and how often the generator places veins of an ore block.
+#[derive(Clone, Debug)]
+pub struct OreDef {
+    // inclusive world height range vein centres are picked from
+    pub minY: i32,
+    pub maxY: i32,
+    // roughly how many blocks one vein has
+    pub size: u32,
+    // veins tried per chunk
+    pub frequency: u32,
+    // blocks a vein may replace, anything else (air, other ores) is left alone
+    pub replaces: Vec<BlockId>,
+}
+
+impl BlockDef {
+    // texture layer and uv quarter turns for world face `d` of a block in `state`
+    pub fn faceTexture(&self, state: BlockState, d: usize) -> (u32, u8) {
+        match self.rotation {
+            Rotation::None => (self.layers[d], 0),
+            Rotation::Axis => {
+                let axis = state.axis() as usize;
+                (self.layers[AXIS_FACES[axis][d]], AXIS_TURNS[axis][d])
+            }
+            Rotation::Facing => {
+                let facing = state.facing();
+                (
+                    self.layers[FACING_FACES[facing][d]],
+                    FACING_TURNS[facing][d],
+                )
+            }
+        }
+    }
+}
+
+/// Every block type the world knows about, loaded from a definitions file.
+/// IDs follow the order of the file, starting at 1 since 0 is always air.
+pub struct BlockRegistry {
+    defs: Vec<BlockDef>,
+    names: HashMap<String, BlockId>,
+    // texture array layers, each path only once
+    textures: Vec<String>,
+}
+
+// on-disk layout, see src/blocks.toml
+#[derive(Deserialize)]
+struct RawDefs {
+    #[serde(default)]
+    model: Vec<RawModel>,
+    block: Vec<RawBlock>,
+}
+
+#[derive(Deserialize)]
+struct RawModel {
+    name: String,
+    #[serde(default, rename = "box")]
+    boxes: Vec<RawBox>,
+    #[serde(default)]
+    cross: bool,
+}
+
+#[derive(Deserialize)]
+struct RawBox {
+    from: [u32; 3],
+    to: [u32; 3],
+    // faces left out, e.g. where two boxes touch
+    #[serde(default)]
+    hide: Vec<String>,
+    #[serde(default)]
+    faces: HashMap<String, RawBoxFace>,
+}
+
+#[derive(Deserialize)]
+struct RawBoxFace {
+    uv: Option<[u32; 4]>,
+    texture: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawBlock {
+    name: String,
+    textures: RawTextures,
+    #[serde(default)]
+    render: RenderClass,
+    #[serde(default)]
+    rotation: Rotation,
+    model: Option<String>,
+    ore: Option<RawOre>,
+    fluid: Option<RawFluid>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFluid {
+    tick_delay: u32,
+    #[serde(default = "default_level_drop")]
+    level_drop: u8,
+    hardens: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawOre {
+    min_y: i32,
+    max_y: i32,
+    size: u32,
+    frequency: u32,
+    #[serde(default = "default_replaces")]
+    replaces: Vec<String>,
+}
+
+// most specific key wins: a direction, then side/top/bottom, then all
+#[derive(Deserialize)]
+struct RawTextures {
+    all: Option<String>,
+    side: Option<String>,
+    top: Option<String>,
+    bottom: Option<String>,
+    back: Option<String>,
+    front: Option<String>,
+    left: Option<String>,
+    right: Option<String>,
+}
+
+fn default_level_drop() -> u8 {
+    1
+}
+
+fn default_replaces() -> Vec<String> {
+    vec!["stone".to_string()]
+}
+
+impl RawTextures {
+    fn resolve(&self, name: &str) -> Result<[&String; 6], String> {
+        let side = self.side.as_ref().or(self.all.as_ref());
+        let faces = [
+            self.back.as_ref().or(side),
+            self.front.as_ref().or(side),
+            self.left.as_ref().or(side),
+            self.right.as_ref().or(side),
+            self.bottom.as_ref().or(self.all.as_ref()),
+            self.top.as_ref().or(self.all.as_ref()),
+        ];
+        let mut out = Vec::with_capacity(6);
+        for (d, face) in faces.into_iter().enumerate() {
+            out.push(face.ok_or_else(|| {
+                format!("block '{}' has no texture for its {} face", name, FACES[d])
+            })?);
+        }
+        Ok(out.try_into().unwrap())
+    }
+}
+
+impl BlockRegistry {
+    pub fn load(path: &str) -> Self {
+        let src = fs::read_to_string(path).unwrap_or_else(|_| panic!("Failed to open {}", path));
+        BlockRegistry::parse(&src).unwrap_or_else(|e| panic!("Failed to load {}: {}", path, e))
+    }
+
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let raw: RawDefs = toml::from_str(src).map_err(|e| e.to_string())?;
+
+        let mut registry = BlockRegistry {
+            defs: vec![BlockDef {
+                name: "air".to_string(),
+                layers: [0; 6],
+                render: RenderClass::Translucent,
+                opaque: false,
+                model: None,
+                rotation: Rotation::None,
+                ore: None,
+                fluid: None,
+            }],
+            names: HashMap::new(),
+            textures: Vec::new(),
+        };
+        registry.names.insert("air".to_string(), BlockId::AIR);
+
+        let mut models = HashMap::new();
+        for model in raw.model {
+            if models.contains_key(&model.name) {
+                return Err(format!("model '{}' is defined twice", model.name));
+            }
+            let built = registry.buildModel(&model)?;
+            models.insert(model.name, built);
+        }
+
+        // ores may name blocks defined further down, so they're resolved at the end
+        let mut ores = Vec::new();
+        let mut fluids = Vec::new();
+        for block in raw.block {
+            if registry.names.contains_key(&block.name) {
+                return Err(format!("block '{}' is defined twice", block.name));
+            }
+            let id = u16::try_from(registry.defs.len())
+                .map(BlockId)
+                .map_err(|_| "too many block types".to_string())?;
+
+            let faces = block.textures.resolve(&block.name)?;
+            let mut layers = [0u32; 6];
+            for (d, path) in faces.iter().enumerate() {
+                layers[d] = registry.textureLayer(path);
+            }
+
+            if let Some(ore) = block.ore {
+                if ore.min_y > ore.max_y {
+                    return Err(format!("ore '{}' has minY above maxY", block.name));
+                }
+                ores.push((id, ore));
+            }
+            if let Some(fluid) = block.fluid {
+                if fluid.tick_delay == 0 || fluid.level_drop == 0 || fluid.level_drop > 7 {
+                    return Err(format!(
+                        "fluid '{}' needs tickDelay above 0 and levelDrop 1-7",
+                        block.name
+                    ));
+                }
+                fluids.push((id, fluid));
+            }
+
+            let model = match &block.model {
+                Some(name) => Some(models.get(name).cloned().ok_or_else(|| {
+                    format!("block '{}' uses unknown model '{}'", block.name, name)
+                })?),
+                None => None,
+            };
+
+            registry.names.insert(block.name.clone(), id);
+            registry.defs.push(BlockDef {
+                name: block.name,
+                layers,
+                render: block.render,
+                opaque: block.render == RenderClass::Opaque && model.is_none(),
+                model,
+                rotation: block.rotation,
+                ore: None,
+                fluid: None,
+            });
+        }
+
+        for (id, ore) in ores {
+            let mut replaces = Vec::with_capacity(ore.replaces.len());
+            for name in &ore.replaces {
+                replaces.push(registry.id(name).ok_or_else(|| {
+                    format!(
+                        "ore '{}' replaces unknown block '{}'",
+                        registry.get(id).name,
+                        name
+                    )
+                })?);
+            }
+            registry.defs[id.0 as usize].ore = Some(OreDef {
+                minY: ore.min_y,
+                maxY: ore.max_y,
+                size: ore.size,
+                frequency: ore.frequency,
+                replaces,
+            });
+        }
+        for (id, fluid) in fluids {
+            let hardens = match &fluid.hardens {
+                Some(name) => Some(registry.id(name).ok_or_else(|| {
+                    format!(
+                        "fluid '{}' hardens into unknown block '{}'",
+                        registry.get(id).name,
+                        name
+                    )
+                })?),
+                None => None,
+            };
+            registry.defs[id.0 as usize].fluid = Some(FluidDef {
+                tickDelay: fluid.tick_delay,
+                levelDrop: fluid.level_drop,
+                hardens,
+            });
+        }
+        Ok(registry)
+    }
+
+    fn buildModel(&mut self, raw: &RawModel) -> Result<BlockModel, String> {
+        if raw.cross {
+            if !raw.boxes.is_empty() {
+                return Err(format!("model '{}' is both a cross and boxes", raw.name));
+            }
+            return Ok(BlockModel::Cross);
+        }
+        if raw.boxes.is_empty() {
+            return Err(format!("model '{}' has no boxes", raw.name));
+        }
+        let mut boxes = Vec::with_capacity(raw.boxes.len());
+        for b in &raw.boxes {
+            if (0..3).any(|i| b.from[i] > b.to[i] || b.to[i] > 16) {
+                return Err(format!(
+                    "model '{}' has a box outside 0-16 or with from above to",
+                    raw.name
+                ));
+            }
+            for name in b.hide.iter().chain(b.faces.keys()) {
+                if !FACES.contains(&name.as_str()) {
+                    return Err(format!("model '{}' has no face '{}'", raw.name, name));
+                }
+            }
+            let mut faces = [None; 6];
+            for (d, face) in faces.iter_mut().enumerate() {
+                if b.hide.iter().any(|h| h == FACES[d]) {
+                    continue;
+                }
+                let custom = b.faces.get(FACES[d]);
+                let uv = match custom.and_then(|f| f.uv) {
+                    Some(uv) if uv.iter().any(|c| *c > 16) => {
+                        return Err(format!("model '{}' has a uv outside 0-16", raw.name));
+                    }
+                    Some(uv) => uv,
+                    None => ModelBox::defaultUv(b.from, b.to, d),
+                };
+                let layer = custom
+                    .and_then(|f| f.texture.as_ref())
+                    .map(|path| self.textureLayer(path));
+                *face = Some(BoxFace { uv, layer });
+            }
+            boxes.push(ModelBox {
+                from: b.from,
+                to: b.to,
+                faces,
+            });
+        }
+        Ok(BlockModel::Boxes(boxes))
+    }
+
+    // layer for a texture path, adding it to the array if it's new
+    fn textureLayer(&mut self, path: &str) -> u32 {
+        match self.textures.iter().position(|t| t == path) {
+            Some(layer) => layer as u32,
+            None => {
+                self.textures.push(path.to_string());
+                (self.textures.len() - 1) as u32
+            }
+        }
+    }
+
+    pub fn get(&self, id: BlockId) -> &BlockDef {
+        &self.defs[id.0 as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.names.get(name).copied()
+    }
+
+    // every block with an ore table, in id order
+    pub fn ores(&self) -> impl Iterator<Item = (BlockId, &OreDef)> {
+        self.defs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, def)| def.ore.as_ref().map(|ore| (BlockId(i as u16), ore)))
+    }
+
+    pub fn isFluid(&self, id: BlockId) -> bool {
+        self.get(id).fluid.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    // paths in layer order, ready for make_texture_array
+    pub fn texturePaths(&self) -> Vec<&str> {
+        self.textures.iter().map(|t| t.as_str()).collect()
+    }
+}
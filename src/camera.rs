@@ -2,7 +2,6 @@
 #![allow(non_snake_case)]
 #![allow(dead_code)]
 
-use cgmath;
 use cgmath::vec3;
 use cgmath::prelude::*;
 
@@ -13,12 +12,12 @@ type Matrix4 = cgmath::Matrix4<f32>;
 // Defines several possible options for camera movement. Used as abstraction to stay away from window-system specific input methods
 #[derive(PartialEq, Clone, Copy)]
 pub enum Camera_Movement {
-    FORWARD,
-    BACKWARD,
-    LEFT,
-    RIGHT,
-    UP,
-    DOWN
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Up,
+    Down
 }
 use self::Camera_Movement::*;
 
@@ -73,22 +72,22 @@ impl Camera {
     /// Processes input received from any keyboard-like input system. Accepts input parameter in the form of camera defined ENUM (to abstract it from windowing systems)
     pub fn ProcessKeyboard(&mut self, direction: Camera_Movement, deltaTime: f32) {
         let velocity = self.MovementSpeed * deltaTime;
-        if direction == FORWARD {
+        if direction == Forward {
             self.Position += self.Front * velocity;
         }
-        if direction == BACKWARD {
+        if direction == Backward {
             self.Position += -(self.Front * velocity);
         }
-        if direction == LEFT {
+        if direction == Left {
             self.Position += -(self.Right * velocity);
         }
-        if direction == RIGHT {
+        if direction == Right {
             self.Position += self.Right * velocity;
         }
-        if direction == UP {
+        if direction == Up {
             self.Position += self.Up * velocity;
         }
-        if direction == DOWN {
+        if direction == Down {
             self.Position -= self.Up * velocity;
         }
     }
@@ -103,12 +102,7 @@ impl Camera {
 
         // Make sure that when pitch is out of bounds, screen doesn't get flipped
         if constrainPitch {
-            if self.Pitch > 89.0 {
-                self.Pitch = 89.0;
-            }
-            if self.Pitch < -89.0 {
-                self.Pitch = -89.0;
-            }
+            self.Pitch = self.Pitch.clamp(-89.0, 89.0);
         }
 
         // Update Front, Right and Up Vectors using the updated Eular angles
@@ -0,0 +1,200 @@
+use cgmath::Vector3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::f64::consts::PI;
+
+//local
+use crate::block::{BlockId, BlockRegistry};
+use crate::generator::chunkSeed;
+use crate::noise::Perlin;
+use crate::world::{CHUNKSIZE, Chunk, ChunkPos};
+
+// how many chunks away a worm can start and still reach this one
+const WORMRANGE: i32 = 8;
+// steps are one block long, so a worm never gets further than this from its start
+const WORMMAXLEN: i32 = WORMRANGE * CHUNKSIZE as i32 - 16;
+
+/// Cuts caves out of already filled terrain: big 3D noise "cheese" caves
+/// plus worm tunnels. A worm is walked from its start chunk every time a
+/// chunk it might pass through is generated, and only the cells inside that
+/// chunk are carved, so tunnels line up no matter which chunk comes first.
+/// Fluids and the blocks holding them up are left alone so seas don't hang
+/// over air.
+pub struct CaveCarver {
+    seed: u64,
+    noise: Perlin,
+    fluids: Vec<BlockId>,
+    // blocks per noise unit horizontally, caves are squashed vertically by flatten
+    pub cheeseScale: f64,
+    pub cheeseFlatten: f64,
+    // noise has to be above this to carve, higher means fewer, smaller caves
+    pub cheeseThreshold: f64,
+    // cheese caves stay this far under the column's surface
+    pub cheeseRoof: i32,
+    // one chunk in wormRarity starts worms
+    pub wormRarity: u32,
+    // worms start between y=8 and this
+    pub wormMaxY: i32,
+}
+
+struct Worm {
+    pos: Vector3<f64>,
+    yaw: f64,
+    pitch: f64,
+    length: i32,
+    // widest radius, reached halfway along
+    radius: f64,
+    // seeds the turns, so the path is the same for every chunk that walks it
+    walkSeed: u64,
+}
+
+impl CaveCarver {
+    pub fn new(seed: u64, registry: &BlockRegistry) -> Self {
+        Self {
+            seed,
+            noise: Perlin::new(seed.wrapping_add(3)),
+            fluids: (0..registry.len() as u16)
+                .map(BlockId)
+                .filter(|id| registry.isFluid(*id))
+                .collect(),
+            cheeseScale: 48.0,
+            cheeseFlatten: 2.0,
+            cheeseThreshold: 0.32,
+            cheeseRoof: 8,
+            wormRarity: 6,
+            wormMaxY: 64,
+        }
+    }
+
+    pub fn carve(&self, pos: ChunkPos, chunk: &mut Chunk) {
+        self.carveCheese(pos, chunk);
+        for cx in pos.x - WORMRANGE..=pos.x + WORMRANGE {
+            for cz in pos.z - WORMRANGE..=pos.z + WORMRANGE {
+                for worm in self.worms(ChunkPos::new(cx, cz)) {
+                    self.carveWorm(worm, pos, chunk);
+                }
+            }
+        }
+    }
+
+    fn carveCheese(&self, pos: ChunkPos, chunk: &mut Chunk) {
+        let height = chunk.height();
+        for x in 0..CHUNKSIZE {
+            for z in 0..CHUNKSIZE {
+                // highest solid block, caves keep a roof under it
+                let Some(top) = (0..height)
+                    .rev()
+                    .find(|&y| chunk.get(Vector3::new(x, y, z)) != BlockId::AIR)
+                else {
+                    continue;
+                };
+                let wx = (pos.x * CHUNKSIZE as i32 + x as i32) as f64 / self.cheeseScale;
+                let wz = (pos.z * CHUNKSIZE as i32 + z as i32) as f64 / self.cheeseScale;
+                let roof = top as i32 - self.cheeseRoof;
+                // y=0 stays as a floor
+                for y in 1..roof.max(1) {
+                    let wy = y as f64 * self.cheeseFlatten / self.cheeseScale;
+                    let cord = Vector3::new(x, y as usize, z);
+                    if self.noise.fbm3(wx, wy, wz, 2) > self.cheeseThreshold
+                        && self.carvable(chunk, cord)
+                    {
+                        chunk.set(cord, BlockId::AIR);
+                    }
+                }
+            }
+        }
+    }
+
+    // worms starting in `origin`, only depends on the seed and origin
+    fn worms(&self, origin: ChunkPos) -> Vec<Worm> {
+        let mut rng = ChaCha8Rng::seed_from_u64(chunkSeed(self.seed, 1, origin));
+        if rng.random_range(0..self.wormRarity) != 0 {
+            return Vec::new();
+        }
+        (0..rng.random_range(1..=3))
+            .map(|_| Worm {
+                pos: Vector3::new(
+                    (origin.x * CHUNKSIZE as i32) as f64 + rng.random_range(0.0..CHUNKSIZE as f64),
+                    rng.random_range(8.0..self.wormMaxY.max(9) as f64),
+                    (origin.z * CHUNKSIZE as i32) as f64 + rng.random_range(0.0..CHUNKSIZE as f64),
+                ),
+                yaw: rng.random_range(0.0..2.0 * PI),
+                pitch: rng.random_range(-0.25..0.25),
+                length: rng.random_range(WORMMAXLEN / 2..=WORMMAXLEN),
+                radius: rng.random_range(1.5..3.5),
+                walkSeed: rng.random(),
+            })
+            .collect()
+    }
+
+    // walks the whole worm, carving the steps that touch `pos`
+    fn carveWorm(&self, mut worm: Worm, pos: ChunkPos, chunk: &mut Chunk) {
+        let mut rng = ChaCha8Rng::seed_from_u64(worm.walkSeed);
+        let (mut yawTurn, mut pitchTurn) = (0.0, 0.0);
+        let minX = (pos.x * CHUNKSIZE as i32) as f64;
+        let minZ = (pos.z * CHUNKSIZE as i32) as f64;
+        let height = chunk.height() as i32;
+
+        for step in 0..worm.length {
+            let r = 1.0 + worm.radius * (PI * step as f64 / worm.length as f64).sin();
+            let p = worm.pos;
+            if p.x + r >= minX
+                && p.x - r < minX + CHUNKSIZE as f64
+                && p.z + r >= minZ
+                && p.z - r < minZ + CHUNKSIZE as f64
+            {
+                self.carveBlob(p, r, pos, chunk, height);
+            }
+
+            worm.pos += Vector3::new(
+                worm.yaw.cos() * worm.pitch.cos(),
+                worm.pitch.sin(),
+                worm.yaw.sin() * worm.pitch.cos(),
+            );
+            // wander, flattening out over time
+            worm.yaw += yawTurn * 0.1;
+            worm.pitch = worm.pitch * 0.8 + pitchTurn * 0.1;
+            yawTurn = yawTurn * 0.75 + rng.random_range(-1.0..1.0);
+            pitchTurn = pitchTurn * 0.9 + rng.random_range(-1.0..1.0) * 0.5;
+        }
+    }
+
+    fn carvable(&self, chunk: &Chunk, cord: Vector3<usize>) -> bool {
+        let block = chunk.get(cord);
+        if block == BlockId::AIR || self.fluids.contains(&block) {
+            return false;
+        }
+        cord.y + 1 >= chunk.height()
+            || !self
+                .fluids
+                .contains(&chunk.get(Vector3::new(cord.x, cord.y + 1, cord.z)))
+    }
+
+    // ellipsoid of radius r (a bit flatter vertically) around p, clipped to the chunk
+    fn carveBlob(&self, p: Vector3<f64>, r: f64, pos: ChunkPos, chunk: &mut Chunk, height: i32) {
+        let ry = r * 0.7;
+        let ox = pos.x * CHUNKSIZE as i32;
+        let oz = pos.z * CHUNKSIZE as i32;
+        let x0 = ((p.x - r).floor() as i32 - ox).max(0);
+        let x1 = ((p.x + r).ceil() as i32 - ox).min(CHUNKSIZE as i32 - 1);
+        let z0 = ((p.z - r).floor() as i32 - oz).max(0);
+        let z1 = ((p.z + r).ceil() as i32 - oz).min(CHUNKSIZE as i32 - 1);
+        let y0 = ((p.y - ry).floor() as i32).max(1);
+        let y1 = ((p.y + ry).ceil() as i32).min(height - 1);
+
+        for x in x0..=x1 {
+            for z in z0..=z1 {
+                for y in y0..=y1 {
+                    // block centres
+                    let dx = ((ox + x) as f64 + 0.5 - p.x) / r;
+                    let dy = (y as f64 + 0.5 - p.y) / ry;
+                    let dz = ((oz + z) as f64 + 0.5 - p.z) / r;
+                    let cord = Vector3::new(x as usize, y as usize, z as usize);
+                    if dx * dx + dy * dy + dz * dz < 1.0 && self.carvable(chunk, cord) {
+                        chunk.set(cord, BlockId::AIR);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use cgmath::{InnerSpace, Vector3};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+//local
+use crate::block::{BlockRegistry, RenderClass};
+use crate::world::{DIRS, IVec3, MeshData, SUBDIV, VERTEXWORDS, World, unpackPos};
+
+/// Quads sharing a texture and render class, with their vertices unpacked
+/// into world space. Every quad has its own four vertices.
+pub struct Group {
+    pub layer: u32,
+    pub class: RenderClass,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    // v up from the bottom of the texture, repeating once per block
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl Group {
+    fn new(layer: u32, class: RenderClass) -> Self {
+        Self {
+            layer,
+            class,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    // material name, the texture's file name plus the class where it isn't opaque
+    fn name(&self, registry: &BlockRegistry) -> String {
+        let path = registry.texturePaths()[self.layer as usize];
+        let stem = Path::new(path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("texture");
+        match self.class {
+            RenderClass::Opaque => stem.to_string(),
+            RenderClass::Cutout => format!("{}_cutout", stem),
+            RenderClass::Translucent => format!("{}_translucent", stem),
+        }
+    }
+}
+
+/// The blocks of a world-space box meshed at full detail and merged into one
+/// mesh, ready to write out as OBJ, PLY or glTF.
+pub struct ExportMesh<'r> {
+    registry: &'r BlockRegistry,
+    pub groups: Vec<Group>,
+}
+
+impl<'r> ExportMesh<'r> {
+    /// Meshes the loaded chunks overlapping blocks `min` to `max` (inclusive).
+    /// Quads are kept when the block they belong to is inside the box.
+    pub fn collect(world: &'r World, min: IVec3, max: IVec3) -> Self {
+        let mut export = Self {
+            registry: world.registry(),
+            groups: Vec::new(),
+        };
+        for (origin, mesh) in world.meshRegion(min, max) {
+            for (class, data) in [
+                (RenderClass::Opaque, &mesh.opaque),
+                (RenderClass::Cutout, &mesh.cutout),
+                (RenderClass::Translucent, &mesh.translucent),
+            ] {
+                export.add(data, class, origin);
+            }
+        }
+        export.groups.sort_by_key(|g| (g.class as u8, g.layer));
+        export
+    }
+
+    // unpacks the quads of `data` into the groups by texture
+    fn add(&mut self, data: &MeshData, class: RenderClass, origin: IVec3) {
+        let offset = origin.cast::<f32>().unwrap();
+        for quad in data.indices.chunks_exact(6) {
+            // pushRaw gives each quad four vertices in a row
+            let first = *quad.iter().min().unwrap() as usize;
+            let words = |c: usize| {
+                let at = (first + c) * VERTEXWORDS;
+                (data.vertices[at], data.vertices[at + 1])
+            };
+            let corners: Vec<Vector3<f32>> = (0..4)
+                .map(|c| unpackPos(words(c).0).cast::<f32>().unwrap() / SUBDIV as f32 + offset)
+                .collect();
+            let (w0, w1) = words(0);
+            let face = ((w0 >> 27) & 7) as usize;
+            let layer = w1 >> 18;
+            // the 6 and 7 of cross planes aren't DIRS, their corners give the way they face
+            let normal = if face < 6 {
+                Vector3::new(DIRS[face][0], DIRS[face][1], DIRS[face][2])
+                    .cast::<f32>()
+                    .unwrap()
+            } else {
+                (corners[1] - corners[0])
+                    .cross(corners[2] - corners[0])
+                    .normalize()
+            };
+
+            let group = match self
+                .groups
+                .iter()
+                .position(|g| g.layer == layer && g.class == class)
+            {
+                Some(i) => &mut self.groups[i],
+                None => {
+                    self.groups.push(Group::new(layer, class));
+                    self.groups.last_mut().unwrap()
+                }
+            };
+            let base = group.positions.len() as u32;
+            for (c, corner) in corners.iter().enumerate() {
+                let w1 = words(c).1;
+                group.positions.push((*corner).into());
+                group.normals.push(normal.into());
+                group.uvs.push([
+                    (w1 & 511) as f32 / SUBDIV as f32,
+                    ((w1 >> 9) & 511) as f32 / SUBDIV as f32,
+                ]);
+            }
+            group
+                .indices
+                .extend(quad.iter().map(|i| *i - first as u32 + base));
+        }
+    }
+
+    pub fn isEmpty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    fn texturePath(&self, group: &Group) -> &str {
+        self.registry.texturePaths()[group.layer as usize]
+    }
+
+    /// Wavefront OBJ with a `usemtl` group per material, and the .mtl next to
+    /// it pointing at the block textures where they are.
+    pub fn writeObj(&self, path: &Path) -> io::Result<()> {
+        let mtlPath = path.with_extension("mtl");
+        let mut mtl = BufWriter::new(File::create(&mtlPath)?);
+        for group in &self.groups {
+            let texture = self.texturePath(group);
+            // absolute, so the .obj can be opened from anywhere
+            let texture = fs::canonicalize(texture)
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|_| texture.to_string());
+            writeln!(mtl, "newmtl {}", group.name(self.registry))?;
+            writeln!(mtl, "Ka 1 1 1\nKd 1 1 1\nKs 0 0 0\nillum 1")?;
+            writeln!(mtl, "map_Kd {}", texture)?;
+            if group.class != RenderClass::Opaque {
+                writeln!(mtl, "map_d {}", texture)?;
+            }
+            writeln!(mtl)?;
+        }
+        mtl.flush()?;
+
+        let mut obj = BufWriter::new(File::create(path)?);
+        let mtlName = mtlPath.file_name().unwrap().to_string_lossy();
+        writeln!(obj, "mtllib {}", mtlName)?;
+        // obj indices are 1 based and count across the whole file
+        let mut base = 1;
+        for group in &self.groups {
+            let name = group.name(self.registry);
+            writeln!(obj, "g {}\nusemtl {}", name, name)?;
+            for p in &group.positions {
+                writeln!(obj, "v {} {} {}", p[0], p[1], p[2])?;
+            }
+            for uv in &group.uvs {
+                writeln!(obj, "vt {} {}", uv[0], uv[1])?;
+            }
+            for n in &group.normals {
+                writeln!(obj, "vn {} {} {}", n[0], n[1], n[2])?;
+            }
+            for tri in group.indices.chunks_exact(3) {
+                let [a, b, c] = [tri[0] + base, tri[1] + base, tri[2] + base];
+                writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+            }
+            base += group.positions.len() as u32;
+        }
+        obj.flush()
+    }
+
+    /// Binary little endian PLY. Materials are listed in comments and each
+    /// triangle carries the index of its own.
+    pub fn writePly(&self, path: &Path) -> io::Result<()> {
+        let vertexCount: usize = self.groups.iter().map(|g| g.positions.len()).sum();
+        let faceCount: usize = self.groups.iter().map(|g| g.indices.len() / 3).sum();
+
+        let mut ply = BufWriter::new(File::create(path)?);
+        writeln!(ply, "ply\nformat binary_little_endian 1.0")?;
+        for (i, group) in self.groups.iter().enumerate() {
+            let name = group.name(self.registry);
+            writeln!(
+                ply,
+                "comment material {} {} {}",
+                i,
+                name,
+                self.texturePath(group)
+            )?;
+        }
+        writeln!(ply, "element vertex {}", vertexCount)?;
+        for prop in ["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+            writeln!(ply, "property float {}", prop)?;
+        }
+        writeln!(ply, "element face {}", faceCount)?;
+        writeln!(ply, "property list uchar uint vertex_indices")?;
+        writeln!(ply, "property ushort material_index")?;
+        writeln!(ply, "end_header")?;
+
+        for group in &self.groups {
+            for v in 0..group.positions.len() {
+                let floats = group.positions[v]
+                    .iter()
+                    .chain(&group.normals[v])
+                    .chain(&group.uvs[v]);
+                for f in floats {
+                    ply.write_all(&f.to_le_bytes())?;
+                }
+            }
+        }
+        let mut base = 0;
+        for (i, group) in self.groups.iter().enumerate() {
+            for tri in group.indices.chunks_exact(3) {
+                ply.write_all(&[3])?;
+                for index in tri {
+                    ply.write_all(&(index + base).to_le_bytes())?;
+                }
+                ply.write_all(&(i as u16).to_le_bytes())?;
+            }
+            base += group.positions.len() as u32;
+        }
+        ply.flush()
+    }
+
+    /// Binary glTF 2.0 with a primitive per material and the block textures
+    /// embedded in the binary chunk.
+    pub fn writeGlb(&self, path: &Path) -> io::Result<()> {
+        let mut bin: Vec<u8> = Vec::new();
+        let mut views = Vec::new();
+        let mut accessors = Vec::new();
+        // adds a buffer view over `bytes`, keeping every view 4 byte aligned
+        let mut view = |bin: &mut Vec<u8>, bytes: &[u8], target: Option<u32>| {
+            let offset = bin.len();
+            bin.extend_from_slice(bytes);
+            bin.resize(bin.len().next_multiple_of(4), 0);
+            views.push(match target {
+                Some(t) => format!(
+                    r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+                    offset,
+                    bytes.len(),
+                    t
+                ),
+                None => format!(
+                    r#"{{"buffer":0,"byteOffset":{},"byteLength":{}}}"#,
+                    offset,
+                    bytes.len()
+                ),
+            });
+            views.len() - 1
+        };
+        let floats =
+            |data: &[f32]| -> Vec<u8> { data.iter().flat_map(|f| f.to_le_bytes()).collect() };
+
+        // one image per texture, shared by its opaque, cutout and translucent materials
+        let mut layers: Vec<u32> = Vec::new();
+        let mut images = Vec::new();
+        for group in &self.groups {
+            if layers.contains(&group.layer) {
+                continue;
+            }
+            let texture = self.texturePath(group);
+            let mime = match Path::new(texture).extension().and_then(|e| e.to_str()) {
+                Some("png") => "image/png",
+                Some("jpg") | Some("jpeg") => "image/jpeg",
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("glTF only embeds png and jpeg textures, not {}", texture),
+                    ));
+                }
+            };
+            let v = view(&mut bin, &fs::read(texture)?, None);
+            images.push(format!(r#"{{"bufferView":{},"mimeType":"{}"}}"#, v, mime));
+            layers.push(group.layer);
+        }
+
+        let mut materials = Vec::new();
+        let mut primitives = Vec::new();
+        for (i, group) in self.groups.iter().enumerate() {
+            let image = layers.iter().position(|l| *l == group.layer).unwrap();
+            let alpha = match group.class {
+                RenderClass::Opaque => "",
+                RenderClass::Cutout => {
+                    r#","alphaMode":"MASK","alphaCutoff":0.5,"doubleSided":true"#
+                }
+                RenderClass::Translucent => r#","alphaMode":"BLEND""#,
+            };
+            materials.push(format!(
+                r#"{{"name":{},"pbrMetallicRoughness":{{"baseColorTexture":{{"index":{}}},"metallicFactor":0,"roughnessFactor":1}}{}}}"#,
+                jsonString(&group.name(self.registry)),
+                image,
+                alpha
+            ));
+
+            let (mut lo, mut hi) = ([f32::MAX; 3], [f32::MIN; 3]);
+            for p in &group.positions {
+                for a in 0..3 {
+                    lo[a] = lo[a].min(p[a]);
+                    hi[a] = hi[a].max(p[a]);
+                }
+            }
+            // gltf textures start at the top, ours at the bottom
+            let uvs: Vec<f32> = group
+                .uvs
+                .iter()
+                .flat_map(|uv| [uv[0], 1.0 - uv[1]])
+                .collect();
+            let count = group.positions.len();
+            let attributes = [
+                (floats(group.positions.as_flattened()), "VEC3", count),
+                (floats(group.normals.as_flattened()), "VEC3", count),
+                (floats(&uvs), "VEC2", count),
+            ];
+            for (a, (bytes, kind, count)) in attributes.iter().enumerate() {
+                let v = view(&mut bin, bytes, Some(34962));
+                // POSITION needs its bounds
+                let bounds = if a == 0 {
+                    format!(
+                        r#","min":[{},{},{}],"max":[{},{},{}]"#,
+                        lo[0], lo[1], lo[2], hi[0], hi[1], hi[2]
+                    )
+                } else {
+                    String::new()
+                };
+                accessors.push(format!(
+                    r#"{{"bufferView":{},"componentType":5126,"count":{},"type":"{}"{}}}"#,
+                    v, count, kind, bounds
+                ));
+            }
+            let indices: Vec<u8> = group.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            let v = view(&mut bin, &indices, Some(34963));
+            accessors.push(format!(
+                r#"{{"bufferView":{},"componentType":5125,"count":{},"type":"SCALAR"}}"#,
+                v,
+                group.indices.len()
+            ));
+            let a = accessors.len() - 4;
+            primitives.push(format!(
+                r#"{{"attributes":{{"POSITION":{},"NORMAL":{},"TEXCOORD_0":{}}},"indices":{},"material":{}}}"#,
+                a,
+                a + 1,
+                a + 2,
+                a + 3,
+                i
+            ));
+        }
+
+        // nearest filtering keeps the pixel art sharp, wrapping repeats merged faces
+        let textures: Vec<String> = (0..images.len())
+            .map(|i| format!(r#"{{"sampler":0,"source":{}}}"#, i))
+            .collect();
+        let mut json = format!(
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"Uniformity voxel engine"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+                r#""meshes":[{{"primitives":[{}]}}],"materials":[{}],"textures":[{}],"#,
+                r#""images":[{}],"samplers":[{{"magFilter":9728,"minFilter":9728}}],"#,
+                r#""accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#
+            ),
+            primitives.join(","),
+            materials.join(","),
+            textures.join(","),
+            images.join(","),
+            accessors.join(","),
+            views.join(","),
+            bin.len()
+        )
+        .into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+
+        // 12 byte header, then the JSON and BIN chunks with 8 byte headers each
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = BufWriter::new(File::create(path)?);
+        glb.write_all(b"glTF")?;
+        glb.write_all(&2u32.to_le_bytes())?;
+        glb.write_all(&(total as u32).to_le_bytes())?;
+        glb.write_all(&(json.len() as u32).to_le_bytes())?;
+        glb.write_all(b"JSON")?;
+        glb.write_all(&json)?;
+        glb.write_all(&(bin.len() as u32).to_le_bytes())?;
+        glb.write_all(b"BIN\0")?;
+        glb.write_all(&bin)?;
+        glb.flush()
+    }
+}
+
+fn jsonString(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Writes blocks `min` to `max` (inclusive) of the loaded world to `path`,
+/// as .obj (plus .mtl), .ply or .glb going by the extension.
+pub fn exportRegion(world: &World, min: IVec3, max: IVec3, path: &str) -> io::Result<()> {
+    let path = Path::new(path);
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    // checked before meshing, which takes a while for big boxes
+    if !matches!(ext.as_deref(), Some("obj") | Some("ply") | Some("glb")) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "export: expected a .obj, .ply or .glb file",
+        ));
+    }
+    let lo = IVec3::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z));
+    let hi = IVec3::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z));
+    let mesh = ExportMesh::collect(world, lo, hi);
+    if mesh.isEmpty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "export: no loaded blocks in that box",
+        ));
+    }
+    match ext.as_deref() {
+        Some("obj") => mesh.writeObj(path),
+        Some("ply") => mesh.writePly(path),
+        _ => mesh.writeGlb(path),
+    }
+}
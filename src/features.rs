@@ -0,0 +1,180 @@
+use cgmath::Vector3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+//local
+use crate::block::{Block, BlockId, BlockRegistry};
+use crate::generator::{PendingWrites, chunkSeed};
+use crate::world::{CHUNKSIZE, Chunk, ChunkPos, IVec3, World};
+
+// keeps feature streams apart from the other stages using chunkSeed
+const FEATURESALT: u64 = 0xfea;
+
+/// Places blocks for a feature rooted in one chunk, in world coordinates.
+/// Blocks inside the chunk go straight in, the rest are queued for the
+/// chunk they land in. Features only ever fill air, so it doesn't matter
+/// whether a neighbour gets its queued blocks before or after its own
+/// features.
+pub struct FeatureWriter<'w> {
+    pos: ChunkPos,
+    chunk: &'w mut Chunk,
+    spill: PendingWrites,
+}
+
+impl<'w> FeatureWriter<'w> {
+    pub fn new(pos: ChunkPos, chunk: &'w mut Chunk) -> Self {
+        Self {
+            pos,
+            chunk,
+            spill: PendingWrites::new(),
+        }
+    }
+
+    pub fn place(&mut self, pos: IVec3, block: Block) {
+        if pos.y < 0 || pos.y >= self.chunk.height() as i32 {
+            return;
+        }
+        let (local, target) = World::blockToLoc(pos);
+        let cord = Vector3::new(local.x as usize, local.y as usize, local.z as usize);
+        if target != self.pos {
+            self.spill.entry(target).or_default().push((cord, block));
+        } else if self.chunk.get(cord) == BlockId::AIR {
+            self.chunk.setBlock(cord, block);
+        }
+    }
+
+    pub fn finish(self) -> PendingWrites {
+        self.spill
+    }
+}
+
+/// Decoration stage for multi-block features: trees on grass, dirt and snow,
+/// and stone boulders. How many of each a chunk tries comes from the biome
+/// of the column picked.
+pub struct FeatureDecorator {
+    seed: u64,
+    log: BlockId,
+    leaves: BlockId,
+    stone: BlockId,
+    // blocks trees and boulders may stand on
+    ground: Vec<BlockId>,
+}
+
+impl FeatureDecorator {
+    pub fn new(seed: u64, registry: &BlockRegistry) -> Self {
+        let lookup = |name: &str| {
+            registry
+                .id(name)
+                .unwrap_or_else(|| panic!("features: no '{}' block defined", name))
+        };
+        Self {
+            seed,
+            log: lookup("log"),
+            leaves: lookup("leaves"),
+            stone: lookup("stone"),
+            ground: vec![lookup("grass"), lookup("dirt"), lookup("snow")],
+        }
+    }
+
+    pub fn decorate(&self, pos: ChunkPos, chunk: &mut Chunk) -> PendingWrites {
+        let mut rng = ChaCha8Rng::seed_from_u64(chunkSeed(self.seed, FEATURESALT, pos));
+        // trees pick from the most wooded biome in the chunk, so a forest edge
+        // isn't bare just because the sample column fell outside it
+        let (mut trees, mut boulders) = (0, 0);
+        for x in (0..CHUNKSIZE).step_by(4) {
+            for z in (0..CHUNKSIZE).step_by(4) {
+                let def = chunk.biome(x, z).def();
+                trees = trees.max(def.trees);
+                boulders = boulders.max(def.boulders);
+            }
+        }
+
+        let mut writer = FeatureWriter::new(pos, chunk);
+        for _ in 0..trees {
+            let (x, z) = (
+                rng.random_range(0..CHUNKSIZE),
+                rng.random_range(0..CHUNKSIZE),
+            );
+            let trunk = rng.random_range(4..=6);
+            if writer.chunk.biome(x, z).def().trees == 0 {
+                continue;
+            }
+            if let Some(ground) = self.ground(&writer, x, z) {
+                self.tree(
+                    &mut writer,
+                    pos,
+                    Vector3::new(x as i32, ground + 1, z as i32),
+                    trunk,
+                );
+            }
+        }
+        for _ in 0..boulders {
+            let (x, z) = (
+                rng.random_range(0..CHUNKSIZE),
+                rng.random_range(0..CHUNKSIZE),
+            );
+            let radius = rng.random_range(1.2..2.6);
+            if writer.chunk.biome(x, z).def().boulders == 0 {
+                continue;
+            }
+            if let Some(ground) = self.ground(&writer, x, z) {
+                self.boulder(
+                    &mut writer,
+                    pos,
+                    Vector3::new(x as i32, ground, z as i32),
+                    radius,
+                );
+            }
+        }
+        writer.finish()
+    }
+
+    // height of the top block of a column if something can stand on it
+    fn ground(&self, writer: &FeatureWriter, x: usize, z: usize) -> Option<i32> {
+        let chunk = &writer.chunk;
+        let top = (0..chunk.height())
+            .rev()
+            .find(|&y| chunk.get(Vector3::new(x, y, z)) != BlockId::AIR)?;
+        self.ground
+            .contains(&chunk.get(Vector3::new(x, top, z)))
+            .then_some(top as i32)
+    }
+
+    // `base` is local to `pos`, the trunk's lowest block
+    fn tree(&self, writer: &mut FeatureWriter, pos: ChunkPos, base: IVec3, trunk: i32) {
+        let origin = Vector3::new(pos.x * CHUNKSIZE as i32, 0, pos.z * CHUNKSIZE as i32) + base;
+        for y in 0..trunk {
+            writer.place(origin + Vector3::new(0, y, 0), Block::from(self.log));
+        }
+        // two wide layers around the top of the trunk, then a small cap
+        let top = origin + Vector3::new(0, trunk, 0);
+        for dy in -2..=1 {
+            let r: i32 = if dy < 0 { 2 } else { 1 };
+            for dx in -r..=r {
+                for dz in -r..=r {
+                    // clip the corners of the wide layers
+                    if r == 2 && dx.abs() == 2 && dz.abs() == 2 {
+                        continue;
+                    }
+                    writer.place(top + Vector3::new(dx, dy, dz), Block::from(self.leaves));
+                }
+            }
+        }
+    }
+
+    // half sunk sphere of stone centred on the ground block at `base`
+    fn boulder(&self, writer: &mut FeatureWriter, pos: ChunkPos, base: IVec3, radius: f64) {
+        let centre = Vector3::new(pos.x * CHUNKSIZE as i32, 0, pos.z * CHUNKSIZE as i32) + base;
+        let r = radius.ceil() as i32;
+        for dx in -r..=r {
+            for dy in -r..=r {
+                for dz in -r..=r {
+                    let d2 = (dx * dx + dy * dy + dz * dz) as f64;
+                    if d2 <= radius * radius {
+                        writer.place(centre + Vector3::new(dx, dy, dz), Block::from(self.stone));
+                    }
+                }
+            }
+        }
+    }
+}
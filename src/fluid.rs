@@ -0,0 +1,141 @@
+use cgmath::Vector3;
+use std::collections::{BTreeMap, HashSet};
+
+//local
+use crate::block::{Block, BlockId, BlockState};
+use crate::world::{IVec3, World};
+
+const UP: IVec3 = Vector3 { x: 0, y: 1, z: 0 };
+const SIDES: [IVec3; 4] = [
+    Vector3 { x: 0, y: 0, z: -1 },
+    Vector3 { x: 0, y: 0, z: 1 },
+    Vector3 { x: -1, y: 0, z: 0 },
+    Vector3 { x: 1, y: 0, z: 0 },
+];
+
+/// Blocks waiting for a fluid update, by the tick they're due on. Only
+/// blocks next to a change ever get queued, so still water costs nothing.
+#[derive(Default)]
+pub struct FluidTicks {
+    now: u64,
+    due: BTreeMap<u64, Vec<IVec3>>,
+    // everything in `due`, so a block is never queued twice
+    queued: HashSet<IVec3>,
+}
+
+impl FluidTicks {
+    pub fn schedule(&mut self, pos: IVec3, delay: u32) {
+        if self.queued.insert(pos) {
+            self.due
+                .entry(self.now + delay as u64)
+                .or_default()
+                .push(pos);
+        }
+    }
+
+    // moves on one tick, returning the blocks due now
+    pub fn advance(&mut self) -> Vec<IVec3> {
+        self.now += 1;
+        let due = self.due.remove(&self.now).unwrap_or_default();
+        for pos in &due {
+            self.queued.remove(pos);
+        }
+        due
+    }
+}
+
+fn fluidState(level: u8, falling: bool) -> BlockState {
+    BlockState::default().withLevel(level).withFalling(falling)
+}
+
+fn isSource(state: BlockState) -> bool {
+    state.level() == 0 && !state.falling()
+}
+
+/// Works out what the fluid at `pos` does this tick, as blocks to set.
+/// Flowing blocks first settle to one level below their best neighbour
+/// (or drain away), then the fluid falls into air below it or, resting on
+/// something solid, spreads sideways one level lower.
+pub fn flow(world: &World, pos: IVec3) -> Vec<(IVec3, Block)> {
+    let registry = world.registry();
+    let id = world.get_block(pos);
+    let Some(fluid) = &registry.get(id).fluid else {
+        return Vec::new();
+    };
+
+    // touching another fluid, e.g. lava next to water
+    if let Some(hardens) = fluid.hardens {
+        let touching = SIDES.iter().chain(&[UP, -UP]).any(|d| {
+            let n = world.get_block(pos + d);
+            n != id && registry.isFluid(n)
+        });
+        if touching {
+            return vec![(pos, Block::from(hardens))];
+        }
+    }
+
+    let mut writes = Vec::new();
+    let mut state = world.get_block_state(pos);
+    if !isSource(state) {
+        let want = if world.get_block(pos + UP) == id {
+            Some(fluidState(0, true))
+        } else {
+            SIDES
+                .iter()
+                .filter(|d| world.get_block(pos + **d) == id)
+                .map(|d| {
+                    let n = world.get_block_state(pos + d);
+                    if isSource(n) || n.falling() {
+                        0
+                    } else {
+                        n.level()
+                    }
+                })
+                .min()
+                .map(|feed| feed + fluid.levelDrop)
+                .filter(|level| *level <= 7)
+                .map(|level| fluidState(level, false))
+        };
+        match want {
+            None => return vec![(pos, Block::AIR)],
+            Some(want) if want != state => {
+                writes.push((pos, Block::new(id, want)));
+                state = want;
+            }
+            Some(_) => {}
+        }
+    }
+
+    let below = world.get_block(pos - UP);
+    if pos.y > 0 && below == BlockId::AIR {
+        writes.push((pos - UP, Block::new(id, fluidState(0, true))));
+        return writes;
+    }
+    // nothing to rest on, or already on fluid
+    if pos.y == 0 || registry.isFluid(below) {
+        return writes;
+    }
+
+    let base = if isSource(state) || state.falling() {
+        0
+    } else {
+        state.level()
+    };
+    let level = base + fluid.levelDrop;
+    if level > 7 {
+        return writes;
+    }
+    for d in SIDES {
+        let n = pos + d;
+        let nid = world.get_block(n);
+        if nid == BlockId::AIR {
+            writes.push((n, Block::new(id, fluidState(level, false))));
+        } else if nid == id {
+            let ns = world.get_block_state(n);
+            if !isSource(ns) && !ns.falling() && ns.level() > level {
+                writes.push((n, Block::new(id, fluidState(level, false))));
+            }
+        }
+    }
+    writes
+}
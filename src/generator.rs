@@ -0,0 +1,182 @@
+use cgmath::Vector3;
+use std::collections::HashMap;
+
+//local
+use crate::biome::{BiomeBlocks, ClimateMap, resolveBiomes};
+use crate::block::{Block, BlockId, BlockRegistry};
+use crate::carver::CaveCarver;
+use crate::features::FeatureDecorator;
+use crate::noise::Perlin;
+use crate::ores::OreDecorator;
+use crate::world::{CHUNKSIZE, Chunk, ChunkPos};
+
+/// Blocks meant for other chunks, by the chunk they land in, as local
+/// coordinates there.
+pub type PendingWrites = HashMap<ChunkPos, Vec<(Vector3<usize>, Block)>>;
+
+/// Fills freshly created chunks, one call per ChunkStatus stage. Chunks are
+/// generated on worker threads in any order, so the output has to depend
+/// only on the generator's own settings and `pos`. Anything decoration puts
+/// outside the chunk (tree crowns and the like) is returned for the world to
+/// hand to the neighbours.
+pub trait WorldGenerator: Send + Sync {
+    fn terrain(&self, pos: ChunkPos, chunk: &mut Chunk);
+
+    fn carve(&self, _pos: ChunkPos, _chunk: &mut Chunk) {}
+
+    fn decorate(&self, _pos: ChunkPos, _chunk: &mut Chunk) -> PendingWrites {
+        PendingWrites::new()
+    }
+}
+
+/// Mixes the world seed, a salt and a chunk position into one RNG seed, so
+/// every stage and every chunk draws its own independent numbers.
+pub fn chunkSeed(seed: u64, salt: u64, pos: ChunkPos) -> u64 {
+    let mut h = seed ^ salt.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    h ^= (pos.x as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h ^= (pos.z as i64 as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    // splitmix64 finaliser
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+fn lookup(registry: &BlockRegistry, name: &str) -> BlockId {
+    registry
+        .id(name)
+        .unwrap_or_else(|| panic!("generator: no '{}' block defined", name))
+}
+
+/// Solid dirt with a grass layer over the top 8 blocks of the column.
+pub struct FlatGenerator {
+    dirt: BlockId,
+    grass: BlockId,
+}
+
+impl FlatGenerator {
+    pub fn new(registry: &BlockRegistry) -> Self {
+        Self {
+            dirt: lookup(registry, "dirt"),
+            grass: lookup(registry, "grass"),
+        }
+    }
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn terrain(&self, _pos: ChunkPos, chunk: &mut Chunk) {
+        let height = chunk.height();
+        for x in 0..CHUNKSIZE {
+            for y in 0..height {
+                for z in 0..CHUNKSIZE {
+                    if y >= height - 8 {
+                        chunk.set(Vector3 { x, y, z }, self.grass);
+                    } else {
+                        chunk.set(Vector3 { x, y, z }, self.dirt);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rolling terrain from a seeded fractal noise heightmap over stone. The
+/// climate map picks a biome per column, which sets the surface blocks and
+/// scales the height, and everything under sea level fills with water over
+/// a sand floor. Caves are carved out of the filled terrain afterwards,
+/// then ores are scattered through what's left and trees and boulders are
+/// put on top.
+pub struct NoiseGenerator {
+    noise: Perlin,
+    climate: ClimateMap,
+    biomes: Vec<BiomeBlocks>,
+    stone: BlockId,
+    sand: BlockId,
+    water: BlockId,
+    pub carver: CaveCarver,
+    pub ores: OreDecorator,
+    pub features: FeatureDecorator,
+    // surface height is baseHeight +- amplitude before biome modifiers
+    pub baseHeight: f64,
+    pub amplitude: f64,
+    // blocks per noise unit, bigger is smoother
+    pub scale: f64,
+    pub octaves: u32,
+    // open air at or below this height is water
+    pub seaLevel: i32,
+}
+
+impl NoiseGenerator {
+    pub fn new(seed: u64, registry: &BlockRegistry) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            climate: ClimateMap::new(seed),
+            biomes: resolveBiomes(registry),
+            stone: lookup(registry, "stone"),
+            sand: lookup(registry, "sand"),
+            water: lookup(registry, "water"),
+            carver: CaveCarver::new(seed, registry),
+            ores: OreDecorator::new(seed, registry),
+            features: FeatureDecorator::new(seed, registry),
+            baseHeight: 64.0,
+            amplitude: 24.0,
+            scale: 96.0,
+            octaves: 5,
+            seaLevel: 60,
+        }
+    }
+
+    // surface height of a world column, may be above the chunk's height
+    fn heightWith(&self, wx: i32, wz: i32, offset: f64, scale: f64) -> i32 {
+        let n = self
+            .noise
+            .fbm2(wx as f64 / self.scale, wz as f64 / self.scale, self.octaves);
+        (self.baseHeight + offset + n * self.amplitude * scale).round() as i32
+    }
+}
+
+impl WorldGenerator for NoiseGenerator {
+    fn terrain(&self, pos: ChunkPos, chunk: &mut Chunk) {
+        let height = chunk.height() as i32;
+        for x in 0..CHUNKSIZE {
+            for z in 0..CHUNKSIZE {
+                let wx = pos.x * CHUNKSIZE as i32 + x as i32;
+                let wz = pos.z * CHUNKSIZE as i32 + z as i32;
+                let climate = self.climate.column(wx, wz);
+                chunk.setBiome(x, z, climate.biome);
+                let rules = self.biomes[climate.biome.0 as usize];
+
+                let surface = self
+                    .heightWith(wx, wz, climate.heightOffset, climate.heightScale)
+                    .clamp(0, height - 1);
+                // sea and lake beds are sand whatever the biome
+                let (top, under) = if surface < self.seaLevel {
+                    (self.sand, self.sand)
+                } else {
+                    (rules.surface, rules.subsurface)
+                };
+                for y in 0..=surface {
+                    let block = if y == surface {
+                        top
+                    } else if y >= surface - rules.depth {
+                        under
+                    } else {
+                        self.stone
+                    };
+                    chunk.set(Vector3::new(x, y as usize, z), block);
+                }
+                for y in surface + 1..=self.seaLevel.min(height - 1) {
+                    chunk.set(Vector3::new(x, y as usize, z), self.water);
+                }
+            }
+        }
+    }
+
+    fn carve(&self, pos: ChunkPos, chunk: &mut Chunk) {
+        self.carver.carve(pos, chunk);
+    }
+
+    fn decorate(&self, pos: ChunkPos, chunk: &mut Chunk) -> PendingWrites {
+        self.ores.decorate(pos, chunk);
+        self.features.decorate(pos, chunk)
+    }
+}
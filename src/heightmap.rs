@@ -0,0 +1,262 @@
+use cgmath::Vector3;
+use png::HasParameters;
+use std::fs::{self, File};
+use std::path::Path;
+
+//local
+use crate::block::{BlockId, BlockRegistry};
+use crate::generator::WorldGenerator;
+use crate::world::{CHUNKSIZE, Chunk, ChunkPos};
+
+/// A grid of height samples, row by row. Images come in as 0..1 whatever
+/// their bit depth, DEM grids keep their own units (usually metres). Cells
+/// with no data are NaN.
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    samples: Vec<f32>,
+    min: f32,
+    max: f32,
+}
+
+impl Heightmap {
+    /// Picks the loader from the extension: .asc/.grd are ESRI ASCII grids,
+    /// anything else goes through the image loaders.
+    pub fn load(path: &str) -> Self {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let map = match ext.as_deref() {
+            Some("asc") | Some("grd") => {
+                let src =
+                    fs::read_to_string(path).unwrap_or_else(|_| panic!("Failed to open {}", path));
+                Heightmap::parseAsciiGrid(&src)
+            }
+            Some("png") => Heightmap::loadPng(path),
+            _ => Heightmap::loadImage(path),
+        };
+        map.unwrap_or_else(|e| panic!("Failed to load heightmap {}: {}", path, e))
+    }
+
+    pub fn new(width: usize, depth: usize, samples: Vec<f32>) -> Result<Self, String> {
+        if width == 0 || depth == 0 {
+            return Err("heightmap is empty".to_string());
+        }
+        if samples.len() != width * depth {
+            return Err(format!(
+                "expected {}x{} samples, got {}",
+                width,
+                depth,
+                samples.len()
+            ));
+        }
+        let (min, max) = samples
+            .iter()
+            .filter(|s| !s.is_nan())
+            .fold((f32::MAX, f32::MIN), |(lo, hi), s| (lo.min(*s), hi.max(*s)));
+        if min > max {
+            return Err("heightmap has no data".to_string());
+        }
+        Ok(Self {
+            width,
+            depth,
+            samples,
+            min,
+            max,
+        })
+    }
+
+    // 8 bit images of any kind, averaged down to grey
+    fn loadImage(path: &str) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| e.to_string())?.to_luma();
+        let (w, h) = img.dimensions();
+        let samples = img.pixels().map(|p| p.data[0] as f32 / 255.0).collect();
+        Heightmap::new(w as usize, h as usize, samples)
+    }
+
+    // the image crate strips PNGs down to 8 bits, so 16 bit ones are read here
+    fn loadPng(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+        if info.bit_depth != png::BitDepth::Sixteen {
+            return Heightmap::loadImage(path);
+        }
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+
+        // alpha is ignored, colour channels are averaged
+        let (channels, colours) = match info.color_type {
+            png::ColorType::Grayscale => (1, 1),
+            png::ColorType::GrayscaleAlpha => (2, 1),
+            png::ColorType::RGB => (3, 3),
+            png::ColorType::RGBA => (4, 3),
+            png::ColorType::Indexed => return Err("16 bit indexed png".to_string()),
+        };
+        let samples = buf
+            .chunks_exact(channels * 2)
+            .map(|px| {
+                let sum: u32 = px[..colours * 2]
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]) as u32)
+                    .sum();
+                sum as f32 / colours as f32 / 65535.0
+            })
+            .collect();
+        Heightmap::new(info.width as usize, info.height as usize, samples)
+    }
+
+    /// ESRI ASCII grid: a `key value` header (ncols, nrows, cellsize and so
+    /// on, only ncols, nrows and NODATA_value are used) then the rows from
+    /// north to south.
+    pub fn parseAsciiGrid(src: &str) -> Result<Self, String> {
+        let mut tokens = src.split_whitespace().peekable();
+        let (mut ncols, mut nrows, mut nodata) = (None, None, None);
+        while let Some(key) = tokens.next_if(|t| t.starts_with(|c: char| c.is_ascii_alphabetic())) {
+            let value = tokens
+                .next()
+                .ok_or_else(|| format!("header '{}' has no value", key))?;
+            let number = |v: &str| {
+                v.parse::<f64>()
+                    .map_err(|_| format!("header '{}' has bad value '{}'", key, v))
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "ncols" => ncols = Some(number(value)? as usize),
+                "nrows" => nrows = Some(number(value)? as usize),
+                "nodata_value" => nodata = Some(number(value)? as f32),
+                // georeferencing, meaningless once it's blocks
+                "xllcorner" | "yllcorner" | "xllcenter" | "yllcenter" | "cellsize" | "dx"
+                | "dy" => {
+                    number(value)?;
+                }
+                _ => return Err(format!("unknown header '{}'", key)),
+            }
+        }
+        let ncols = ncols.ok_or("missing ncols")?;
+        let nrows = nrows.ok_or("missing nrows")?;
+        let samples = tokens
+            .map(|t| {
+                let v = t
+                    .parse::<f32>()
+                    .map_err(|_| format!("bad sample '{}'", t))?;
+                Ok(if Some(v) == nodata { f32::NAN } else { v })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Heightmap::new(ncols, nrows, samples)
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.depth)
+    }
+
+    // lowest and highest sample, ignoring missing data
+    pub fn range(&self) -> (f32, f32) {
+        (self.min, self.max)
+    }
+
+    fn get(&self, x: usize, z: usize) -> f32 {
+        self.samples[z * self.width + x]
+    }
+
+    /// Bilinear sample at a fractional grid position, None outside the map or
+    /// next to a missing cell. Samples are cell centres, so the outer half
+    /// cell takes the edge value.
+    pub fn sample(&self, x: f64, z: f64) -> Option<f32> {
+        let (w, d) = (self.width as f64, self.depth as f64);
+        if x < -0.5 || z < -0.5 || x >= w - 0.5 || z >= d - 0.5 {
+            return None;
+        }
+        let (x, z) = (x.clamp(0.0, w - 1.0), z.clamp(0.0, d - 1.0));
+        let (x0, z0) = (x.floor() as usize, z.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(self.width - 1), (z0 + 1).min(self.depth - 1));
+        let (fx, fz) = ((x - x0 as f64) as f32, (z - z0 as f64) as f32);
+        let top = self.get(x0, z0) * (1.0 - fx) + self.get(x1, z0) * fx;
+        let bottom = self.get(x0, z1) * (1.0 - fx) + self.get(x1, z1) * fx;
+        let h = top * (1.0 - fz) + bottom * fz;
+        (!h.is_nan()).then_some(h)
+    }
+}
+
+/// Terrain read from a heightmap instead of noise. Columns outside the map,
+/// or over missing data, are left empty.
+pub struct HeightmapGenerator {
+    map: Heightmap,
+    // blocks per map sample
+    pub horizontalScale: f64,
+    // blocks per unit of sample value, defaults to fitting the map's range in 96 blocks
+    pub verticalScale: f64,
+    // world x and z of the map's first sample, defaults to centring it on the origin
+    pub originX: f64,
+    pub originZ: f64,
+    // height of the lowest sample
+    pub baseHeight: f64,
+    // surface blocks, each filling down to its depth under the surface (0 is
+    // the top block), with `fill` below the last
+    pub layers: Vec<(i32, BlockId)>,
+    pub fill: BlockId,
+}
+
+impl HeightmapGenerator {
+    pub fn new(map: Heightmap, registry: &BlockRegistry) -> Self {
+        let lookup = |name: &str| {
+            registry
+                .id(name)
+                .unwrap_or_else(|| panic!("heightmap: no '{}' block defined", name))
+        };
+        let (min, max) = map.range();
+        let (w, d) = map.size();
+        Self {
+            horizontalScale: 1.0,
+            verticalScale: if max > min {
+                96.0 / (max - min) as f64
+            } else {
+                1.0
+            },
+            originX: -(w as f64) / 2.0,
+            originZ: -(d as f64) / 2.0,
+            baseHeight: 16.0,
+            layers: vec![(0, lookup("grass")), (3, lookup("dirt"))],
+            fill: lookup("stone"),
+            map,
+        }
+    }
+
+    // surface height of a world column, None where the map has nothing
+    fn height(&self, wx: i32, wz: i32) -> Option<i32> {
+        // block centres
+        let sx = (wx as f64 + 0.5 - self.originX) / self.horizontalScale - 0.5;
+        let sz = (wz as f64 + 0.5 - self.originZ) / self.horizontalScale - 0.5;
+        let v = self.map.sample(sx, sz)?;
+        let (min, _) = self.map.range();
+        Some((self.baseHeight + (v - min) as f64 * self.verticalScale).round() as i32)
+    }
+
+    fn blockAt(&self, depth: i32) -> BlockId {
+        self.layers
+            .iter()
+            .find(|(bottom, _)| depth <= *bottom)
+            .map_or(self.fill, |(_, block)| *block)
+    }
+}
+
+impl WorldGenerator for HeightmapGenerator {
+    fn terrain(&self, pos: ChunkPos, chunk: &mut Chunk) {
+        let height = chunk.height() as i32;
+        for x in 0..CHUNKSIZE {
+            for z in 0..CHUNKSIZE {
+                let wx = pos.x * CHUNKSIZE as i32 + x as i32;
+                let wz = pos.z * CHUNKSIZE as i32 + z as i32;
+                let Some(surface) = self.height(wx, wz) else {
+                    continue;
+                };
+                // columns taller than the chunk still get their top layer at the top
+                let top = surface.clamp(0, height - 1);
+                for y in 0..=top {
+                    chunk.set(Vector3::new(x, y as usize, z), self.blockAt(top - y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+#![allow(non_upper_case_globals)]
+#![allow(non_snake_case)]
+//! GL-free core of the voxel engine: block storage, world generation,
+//! meshing into MeshData and coordinates. The GL renderer sits behind the
+//! `render` feature, the window lives in the binary.
+
+pub mod biome;
+pub mod block;
+pub mod carver;
+pub mod export;
+pub mod features;
+pub mod fluid;
+pub mod generator;
+pub mod heightmap;
+pub mod lod;
+pub mod mesher;
+pub mod model;
+pub mod noise;
+pub mod ores;
+pub mod palette;
+pub mod region;
+pub mod world;
+
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "render")]
+pub mod shader;
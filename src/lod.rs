@@ -0,0 +1,188 @@
+use cgmath::{ElementWise, Vector3};
+
+//local
+use crate::block::{Block, BlockId, BlockRegistry, RenderClass};
+use crate::mesher::{Face, FaceGrid, Mesher};
+use crate::world::{CHUNKSIZE, Chunk, DIRS, SECTIONSIZE, SectionMesh};
+
+// detail levels past full detail, cells 2, 4 and 8 blocks across
+pub const LODLEVELS: u8 = 3;
+
+/// Which block a downsampled cell is drawn as. Either way a cell is only
+/// there if at least half of it is filled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LodPick {
+    // the most common block in the cell
+    Majority,
+    // the highest block in the cell, so grass stays green from afar
+    #[default]
+    Surface,
+}
+
+/// A chunk column downsampled into cells `scale` blocks across. Level 0
+/// is the chunk's own blocks, minus models.
+pub struct LodGrid {
+    scale: usize,
+    // cells per side horizontally and vertically
+    width: usize,
+    height: usize,
+    // indexed (x * height + y) * width + z, like Section::blockIndex
+    cells: Vec<Block>,
+}
+
+impl LodGrid {
+    pub fn build(chunk: &Chunk, registry: &BlockRegistry, level: u8, pick: LodPick) -> Self {
+        let scale = 1 << level;
+        let (width, height) = (CHUNKSIZE / scale, chunk.height() / scale);
+        let mut grid = Self {
+            scale,
+            width,
+            height,
+            cells: vec![Block::AIR; width * height * width],
+        };
+        // block ids seen in the current cell and how often
+        let mut counts: Vec<(Block, usize)> = Vec::new();
+        for cx in 0..width {
+            for cz in 0..width {
+                for cy in 0..height {
+                    counts.clear();
+                    // highest first, so the first block found is the surface one
+                    for y in (cy * scale..(cy + 1) * scale).rev() {
+                        for x in cx * scale..(cx + 1) * scale {
+                            for z in cz * scale..(cz + 1) * scale {
+                                let block = chunk.getBlock(Vector3::new(x, y, z));
+                                // models are too small to show at a distance
+                                if block.id == BlockId::AIR
+                                    || registry.get(block.id).model.is_some()
+                                {
+                                    continue;
+                                }
+                                match counts.iter_mut().find(|(b, _)| b.id == block.id) {
+                                    Some((_, n)) => *n += 1,
+                                    None => counts.push((block, 1)),
+                                }
+                            }
+                        }
+                    }
+                    let filled: usize = counts.iter().map(|(_, n)| n).sum();
+                    if filled * 2 < scale * scale * scale {
+                        continue;
+                    }
+                    let block = match pick {
+                        LodPick::Surface => counts[0].0,
+                        LodPick::Majority => {
+                            // max_by_key keeps the last of equals, ties go to the higher block
+                            counts.iter().rev().max_by_key(|(_, n)| *n).unwrap().0
+                        }
+                    };
+                    grid.cells[(cx * height + cy) * width + cz] = block;
+                }
+            }
+        }
+        grid
+    }
+
+    // AIR above and below the column
+    fn get(&self, x: usize, y: i32, z: usize) -> Block {
+        if y < 0 || y as usize >= self.height {
+            return Block::AIR;
+        }
+        self.cells[(x * self.height + y as usize) * self.width + z]
+    }
+
+    /// True if the cells over the chunk local blocks `lo` to `hi` (inclusive)
+    /// hide a face of `id` looking into them: all opaque, or the same block
+    /// for blocks that don't show faces inside themselves.
+    pub fn covers(
+        &self,
+        registry: &BlockRegistry,
+        id: BlockId,
+        lo: Vector3<usize>,
+        hi: Vector3<usize>,
+    ) -> bool {
+        let s = self.scale;
+        (lo.x / s..=hi.x / s).all(|x| {
+            (lo.y / s..=hi.y / s)
+                .all(|y| (lo.z / s..=hi.z / s).all(|z| self.hides(registry, id, x, y as i32, z)))
+        })
+    }
+
+    // same as covers() for a single cell
+    fn hides(&self, registry: &BlockRegistry, id: BlockId, x: usize, y: i32, z: usize) -> bool {
+        let def = registry.get(id);
+        let merges = def.fluid.is_some() || def.render == RenderClass::Translucent;
+        let n = self.get(x, y, z);
+        registry.get(n.id).opaque || (merges && n.id == id)
+    }
+
+    /// Faces of the cells in section `sy`. `borders` are the chunks across the
+    /// back, front, left and right (the first four DIRS) at whatever level
+    /// they're drawn at, None where there's nothing drawn. Faces on the edge
+    /// are culled against what the neighbour actually shows, so levels meet
+    /// without cracks. `grid` is scratch space, reset before use.
+    pub fn meshSection(
+        &self,
+        sy: usize,
+        borders: &[Option<LodGrid>; 4],
+        registry: &BlockRegistry,
+        mesher: Mesher,
+        grid: &mut FaceGrid,
+    ) -> SectionMesh {
+        let mut mesh = SectionMesh::default();
+        grid.reset(self.scale as u32);
+        let perSection = SECTIONSIZE / self.scale;
+        let last = self.width as i32 - 1;
+
+        for x in 0..self.width {
+            for ly in 0..perSection {
+                for z in 0..self.width {
+                    let y = (sy * perSection + ly) as i32;
+                    let cell = self.get(x, y, z);
+                    if cell.id == BlockId::AIR {
+                        continue;
+                    }
+                    let def = registry.get(cell.id);
+                    // blocks the cell covers
+                    let lo = Vector3::new(x, y as usize, z) * self.scale;
+                    let hi = lo.add_element_wise(self.scale - 1);
+
+                    for (d, dir) in DIRS.iter().enumerate() {
+                        let nx = x as i32 + dir[0] as i32;
+                        let nz = z as i32 + dir[2] as i32;
+                        let hidden = if nx >= 0 && nz >= 0 && nx <= last && nz <= last {
+                            let ny = y + dir[1] as i32;
+                            self.hides(registry, cell.id, nx as usize, ny, nz as usize)
+                        } else {
+                            // the layer of blocks just over the edge, in the neighbour's coordinates
+                            let (mut nlo, mut nhi) = (lo, hi);
+                            let axis = if dir[0] != 0 { 0 } else { 2 };
+                            let edge = if dir[axis] < 0 { CHUNKSIZE - 1 } else { 0 };
+                            (nlo[axis], nhi[axis]) = (edge, edge);
+                            borders[d]
+                                .as_ref()
+                                .is_some_and(|other| other.covers(registry, cell.id, nlo, nhi))
+                        };
+                        if hidden {
+                            continue;
+                        }
+                        let (layer, turns) = def.faceTexture(cell.state, d);
+                        let face = Face {
+                            class: def.render,
+                            layer,
+                            turns,
+                            height: 1.0,
+                            ao: [3; 4],
+                        };
+                        grid.set(d, x, ly, z, face);
+                    }
+                }
+            }
+        }
+
+        match mesher {
+            Mesher::Naive => grid.pushEach(&mut mesh),
+            Mesher::Greedy => grid.merge(&mut mesh),
+        }
+        mesh
+    }
+}
@@ -5,28 +5,98 @@ extern crate glfw;
 use self::glfw::Context;
 extern crate gl;
 use cgmath::Matrix4;
-use cgmath::{Deg, Point3, Vector2, Vector3, perspective};
-use std::sync::Arc;
+use cgmath::{Deg, Point3, perspective};
 
 // Local
-mod shader;
 use glfw::fail_on_errors;
-use shader::Shader;
 mod common;
 use common::{process_events, processInput};
 mod camera;
 use camera::Camera;
-mod world;
-use crate::world::World;
+use transformations::block::BlockRegistry;
+use transformations::export::exportRegion;
+use transformations::generator::{FlatGenerator, NoiseGenerator, WorldGenerator};
+use transformations::heightmap::{Heightmap, HeightmapGenerator};
+use transformations::lod::LodPick;
+use transformations::mesher::Mesher;
+use transformations::render::WorldRenderer;
+use transformations::shader::Shader;
+use transformations::world::{BLOCKDEFS, IVec3, TICKLENGTH, World};
 
 // settings
 const SCR_WIDTH: u32 = 800;
 const SCR_HEIGHT: u32 = 600;
+const SEED: u64 = 1337;
 
 #[allow(non_snake_case)]
 pub fn main() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    // --flat for the old flat test world, --heightmap <file> for an image or DEM
+    let args: Vec<String> = std::env::args().collect();
+    let heightmap = args
+        .iter()
+        .position(|a| a == "--heightmap")
+        .map(|i| args.get(i + 1).expect("--heightmap needs a file"));
+    let generator: Box<dyn WorldGenerator> = if let Some(path) = heightmap {
+        Box::new(HeightmapGenerator::new(Heightmap::load(path), &registry))
+    } else if args.iter().any(|a| a == "--flat") {
+        Box::new(FlatGenerator::new(&registry))
+    } else {
+        Box::new(NoiseGenerator::new(SEED, &registry))
+    };
+    let mut world = World::new(registry, generator);
+    // --naive meshes one quad per face, to compare against the greedy mesher
+    if args.iter().any(|a| a == "--naive") {
+        world.setMesher(Mesher::Naive);
+    }
+    // --lod <chunks> sets how many rings past the render distance are drawn at lower
+    // detail (0 for none), --lod-majority draws each cell as its most common block
+    if let Some(i) = args.iter().position(|a| a == "--lod") {
+        let rings = args
+            .get(i + 1)
+            .and_then(|n| n.parse().ok())
+            .expect("--lod needs a number of chunks");
+        world.setLodDistance(rings);
+    }
+    if args.iter().any(|a| a == "--lod-majority") {
+        world.setLodPick(LodPick::Majority);
+    }
+    world.setAll();
+    world.chunkRemeshAll();
+    // one line for the whole world, --memory lists every chunk
+    let reports = world.memoryReport();
+    if args.iter().any(|a| a == "--memory") {
+        for (pos, report) in &reports {
+            println!("chunk {:?}: {}", pos, report);
+        }
+    }
+    let count = reports.len();
+    if let Some(total) = reports.into_iter().map(|(_, r)| r).reduce(|a, b| a + b) {
+        println!("{} chunks: {}", count, total);
+    }
+    // --export <file> x0 y0 z0 x1 y1 z1 writes the blocks between the two corners
+    // as .obj, .ply or .glb and quits without opening a window. only what's loaded
+    // around the spawn is there
+    if let Some(i) = args.iter().position(|a| a == "--export") {
+        let path = args.get(i + 1).expect("--export needs a file");
+        let corners: Vec<i32> = args
+            .iter()
+            .skip(i + 2)
+            .take(6)
+            .map(|n| n.parse().expect("--export needs two corners, x y z x y z"))
+            .collect();
+        assert!(corners.len() == 6, "--export needs two corners, x y z x y z");
+        let min = IVec3::new(corners[0], corners[1], corners[2]);
+        let max = IVec3::new(corners[3], corners[4], corners[5]);
+        match exportRegion(&world, min, max, path) {
+            Ok(()) => println!("exported {:?} to {:?} into {}", min, max, path),
+            Err(e) => eprintln!("Failed to export {}: {}", path, e),
+        }
+        return;
+    }
+
     let mut camera = Camera {
-        Position: Point3::new(0.0, 0.0, 0.0),
+        Position: Point3::new(0.0, 130.0, 0.0),
         ..Camera::default()
     };
 
@@ -79,10 +149,14 @@ pub fn main() {
     }
 
     let chunkShader = Shader::new("src/shaders/shaderAtlas.vs", "src/shaders/shaderAtlas.fs");
+    let mut renderer = WorldRenderer::new(chunkShader, world.registry());
+    renderer.sync(&mut world);
+    println!("{} quads ({:?} mesher)", renderer.quadCount(), world.mesher());
 
-    let mut world = World::new(&chunkShader);
-    world.setAll();
-    world.chunkRemeshAll();
+    let mut renderDistance = world.renderDistance();
+    let mut mesher = world.mesher();
+    // time not yet used up by world ticks
+    let mut tickTime = 0.0;
 
     // let mut chunk = Chunk::new(&chunkShader, Vector2 { x: 0.0, y: 0.0 });
 
@@ -113,18 +187,35 @@ pub fn main() {
 
         // events
         // -----
-        process_events(
+        let report = process_events(
             &events,
             &mut firstMouse,
             &mut lastX,
             &mut lastY,
             &mut camera,
+            &mut renderDistance,
+            &mut mesher,
         );
 
         // input
         // -----
         processInput(&mut window, deltaTime, &mut camera);
 
+        // streaming
+        // ---------
+        world.setRenderDistance(renderDistance);
+        world.setMesher(mesher);
+        world.update(camera.Position);
+        renderer.sync(&mut world);
+
+        // simulation
+        // ----------
+        tickTime += deltaTime;
+        while tickTime >= TICKLENGTH {
+            world.tick();
+            tickTime -= TICKLENGTH;
+        }
+
         // render
         // ------
         unsafe {
@@ -139,17 +230,22 @@ pub fn main() {
             );
             let view = camera.GetViewMatrix();
 
-            let pos = camera.Position;
+            if report {
+                let pos = camera.Position;
+                let biome = world
+                    .get_biome(pos.x.floor() as i32, pos.z.floor() as i32)
+                    .map(|b| b.def().name);
+                println!(
+                    "world position: {:?}, local position: {:?}, biome: {:?}, quads: {} ({:?})",
+                    pos,
+                    World::worldToLoc(pos),
+                    biome,
+                    renderer.quadCount(),
+                    mesher
+                );
+            }
 
-            println!("world position: {:?}, local position: {:?}", pos, World::worldToLoc(pos));
-
-            // println!("{:?}", pos);
-
-            // let front = camera.Front;
-            //
-            // println!("{:?}", front);
-
-            world.renderAll(&projection, &view);
+            renderer.render(&world, &projection, &view);
             // chunk.draw(&projection, &view);
         }
 
@@ -158,4 +254,8 @@ pub fn main() {
         window.swap_buffers();
         glfw.poll_events();
     }
+
+    if let Err(e) = world.saveModified() {
+        eprintln!("Failed to save the world: {}", e);
+    }
 }
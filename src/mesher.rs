@@ -0,0 +1,150 @@
+use cgmath::Vector3;
+
+//local
+use crate::block::RenderClass;
+use crate::world::{CHUNKSIZE, FACEAXES, SECTIONSIZE, SectionMesh};
+
+/// How `Chunk::remesh` turns visible faces into quads. Both give the same
+/// picture, Naive is kept to compare against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Mesher {
+    // one quad per visible face
+    Naive,
+    // coplanar neighbouring faces that look the same become one quad
+    #[default]
+    Greedy,
+}
+
+/// Everything about a visible block face that decides how it's drawn. Two
+/// faces can only merge if these match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Face {
+    pub class: RenderClass,
+    pub layer: u32,
+    pub turns: u8,
+    // top edge height for fluids that aren't full, 1 otherwise
+    pub height: f32,
+    // light at each corner, in DIRS corner order, see Chunk::faceAo
+    pub ao: [u8; 4],
+}
+
+impl Face {
+    pub fn push(&self, mesh: &mut SectionMesh, d: usize, at: Vector3<u32>, size: Vector3<u32>) {
+        mesh.class(self.class).pushQuad(d, at, size, self);
+    }
+}
+
+/// The visible faces of one section, collected before merging them. About
+/// half a megabyte, so meshing keeps one around and resets it per section.
+pub struct FaceGrid {
+    // indexed [d][x][y][z]
+    faces: Vec<Option<Face>>,
+    // blocks per cell, above 1 for LOD meshes
+    scale: u32,
+}
+
+impl Default for FaceGrid {
+    fn default() -> Self {
+        FaceGrid::new()
+    }
+}
+
+impl FaceGrid {
+    pub fn new() -> Self {
+        Self {
+            faces: vec![None; 6 * CHUNKSIZE * SECTIONSIZE * CHUNKSIZE],
+            scale: 1,
+        }
+    }
+
+    // empties the grid for the next section, with cells `scale` blocks across. only
+    // the first CHUNKSIZE / scale along each axis are used
+    pub fn reset(&mut self, scale: u32) {
+        self.faces.fill(None);
+        self.scale = scale;
+    }
+
+    fn index(d: usize, cell: [usize; 3]) -> usize {
+        ((d * CHUNKSIZE + cell[0]) * SECTIONSIZE + cell[1]) * CHUNKSIZE + cell[2]
+    }
+
+    pub fn set(&mut self, d: usize, x: usize, y: usize, z: usize, face: Face) {
+        self.faces[FaceGrid::index(d, [x, y, z])] = Some(face);
+    }
+
+    /// One quad per face.
+    pub fn pushEach(&self, mesh: &mut SectionMesh) {
+        for (i, face) in self.faces.iter().enumerate() {
+            let Some(face) = face else {
+                continue;
+            };
+            let z = i % CHUNKSIZE;
+            let y = i / CHUNKSIZE % SECTIONSIZE;
+            let x = i / (CHUNKSIZE * SECTIONSIZE) % CHUNKSIZE;
+            let d = i / (CHUNKSIZE * SECTIONSIZE * CHUNKSIZE);
+            let at = Vector3::new(x as u32, y as u32, z as u32);
+            face.push(mesh, d, at * self.scale, Vector3::new(1, 1, 1) * self.scale);
+        }
+    }
+
+    /// Sweeps each slice of each direction, growing a quad first along the
+    /// face's u axis and then along v for as long as every face it covers
+    /// matches. CHUNKSIZE and SECTIONSIZE are equal so every slice is square.
+    pub fn merge(&self, mesh: &mut SectionMesh) {
+        const N: usize = CHUNKSIZE;
+        for (d, &(ua, va)) in FACEAXES.iter().enumerate() {
+            let na = 3 - ua - va;
+            let cell = |slice: usize, u: usize, v: usize| {
+                let mut c = [0; 3];
+                c[na] = slice;
+                c[ua] = u;
+                c[va] = v;
+                c
+            };
+            for slice in 0..N {
+                let mut used = [[false; N]; N];
+                for v in 0..N {
+                    for u in 0..N {
+                        if used[u][v] {
+                            continue;
+                        }
+                        let Some(face) = self.faces[FaceGrid::index(d, cell(slice, u, v))] else {
+                            continue;
+                        };
+                        let same = |u: usize, v: usize, used: &[[bool; N]; N]| {
+                            !used[u][v]
+                                && self.faces[FaceGrid::index(d, cell(slice, u, v))] == Some(face)
+                        };
+
+                        // uneven shading would get stretched over the whole quad
+                        let even = face.ao.iter().all(|a| *a == face.ao[0]);
+                        let mut w = 1;
+                        while even && u + w < N && same(u + w, v, &used) {
+                            w += 1;
+                        }
+                        // lowered fluid sides can't stack, their top edge isn't the block's
+                        let stacks = even && !(face.height < 1.0 && d < 4);
+                        let mut h = 1;
+                        while stacks && v + h < N && (u..u + w).all(|k| same(k, v + h, &used)) {
+                            h += 1;
+                        }
+                        for row in used.iter_mut().skip(u).take(w) {
+                            row[v..v + h].fill(true);
+                        }
+
+                        let at = cell(slice, u, v);
+                        let mut size = [1; 3];
+                        size[ua] = w as u32;
+                        size[va] = h as u32;
+                        face.push(
+                            mesh,
+                            d,
+                            Vector3::new(at[0] as u32, at[1] as u32, at[2] as u32) * self.scale,
+                            Vector3::from(size) * self.scale,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use cgmath::Vector3;
+
+//local
+use crate::block::{BlockDef, BlockState, Rotation};
+use crate::world::{FACEAXES, MeshData, SUBDIV, vertices};
+
+/// Shape of a block that isn't a full cube, in sixteenths of a block.
+#[derive(Clone, Debug)]
+pub enum BlockModel {
+    // axis aligned boxes (slabs, stairs, fences, torches)
+    Boxes(Vec<ModelBox>),
+    // two crossed planes through the cell's diagonals (flowers, grass)
+    Cross,
+}
+
+/// One box of a model, from and to are corners in 0..=16.
+#[derive(Clone, Debug)]
+pub struct ModelBox {
+    pub from: [u32; 3],
+    pub to: [u32; 3],
+    // per face in DIRS order, None leaves the face out
+    pub faces: [Option<BoxFace>; 6],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoxFace {
+    // texture rectangle [u0, v0, u1, v1] in sixteenths, v counted from the bottom
+    pub uv: [u32; 4],
+    // texture array layer, None takes the block's own texture for the face
+    pub layer: Option<u32>,
+}
+
+// where u and v of face `d` run on the full cube: the axis and whether they
+// count down from the far side
+fn uvAxis(d: usize) -> [(usize, bool); 2] {
+    let (ua, va) = FACEAXES[d];
+    let corner = 120 / 6 * d;
+    // corner 0 sits at u = 0 or 1, and at the low or high end of the axis
+    let flips =
+        |axis: usize, uv: usize| (vertices[corner + axis] > 0.0) != (vertices[corner + uv] > 0.0);
+    [(ua, flips(ua, 3)), (va, flips(va, 4))]
+}
+
+impl ModelBox {
+    /// The part of the texture the box would cover if the block were a full
+    /// cube, so a slab's side shows the lower half of the texture.
+    pub fn defaultUv(from: [u32; 3], to: [u32; 3], d: usize) -> [u32; 4] {
+        let mut uv = [0; 4];
+        for (i, (axis, flip)) in uvAxis(d).into_iter().enumerate() {
+            let (lo, hi) = (from[axis], to[axis]);
+            (uv[i], uv[i + 2]) = if flip { (16 - hi, 16 - lo) } else { (lo, hi) };
+        }
+        uv
+    }
+
+    // does face `d` lie on the cell's boundary, where a neighbour can hide it
+    fn onBoundary(&self, d: usize) -> bool {
+        let axis = [2, 2, 0, 0, 1, 1][d];
+        match d {
+            // back, left and bottom are on the low side
+            0 | 2 | 4 => self.from[axis] == 0,
+            _ => self.to[axis] == 16,
+        }
+    }
+}
+
+// quarter turns about y taking the default front (+Z) facing to `facing`, in
+// DIRS order. models only turn around y, so up and down face front
+const FACING_QUARTERS: [usize; 6] = [2, 0, 3, 1, 0, 0];
+
+// a model position (in sixteenths) turned `quarters` quarter turns about the
+// block's vertical centre line, each turn taking front to right
+fn turn(p: [u32; 3], quarters: usize) -> [u32; 3] {
+    let [mut x, y, mut z] = p;
+    for _ in 0..quarters {
+        (x, z) = (z, 16 - x);
+    }
+    [x, y, z]
+}
+
+// DIRS index a model face direction ends up in after `quarters` turns
+fn turnDir(d: usize, quarters: usize) -> usize {
+    // back, right, front, left is the order one turn steps through
+    const RING: [usize; 4] = [0, 3, 1, 2];
+    match RING.iter().position(|r| *r == d) {
+        Some(i) => RING[(i + 4 - quarters % 4) % 4],
+        None => d,
+    }
+}
+
+impl BlockModel {
+    /// Adds the model for a block in `state` at `at` (section local, in
+    /// blocks) to `mesh`. Box faces flush with the cell are left out when
+    /// `hidden` says the neighbour in that world direction covers them.
+    pub fn bake(
+        &self,
+        def: &BlockDef,
+        state: BlockState,
+        mesh: &mut MeshData,
+        at: Vector3<u32>,
+        hidden: impl Fn(usize) -> bool,
+    ) {
+        let origin = at * SUBDIV;
+        let boxes = match self {
+            BlockModel::Boxes(boxes) => boxes,
+            BlockModel::Cross => {
+                // side texture, both planes are seen from either side
+                let layer = def.layers[1];
+                let uvs = [[0, 0], [16, 0], [16, 16], [0, 16]];
+                for (face, [x0, z0, x1, z1]) in [(6, [2, 2, 14, 14]), (7, [2, 14, 14, 2])] {
+                    let corners = [[x0, 0, z0], [x1, 0, z1], [x1, 16, z1], [x0, 16, z0]]
+                        .map(|p| origin + Vector3::from(p));
+                    mesh.pushRaw(face, corners, uvs, layer, [3; 4]);
+                }
+                return;
+            }
+        };
+
+        let quarters = if def.rotation == Rotation::Facing {
+            FACING_QUARTERS[state.facing()]
+        } else {
+            0
+        };
+        for b in boxes {
+            for (d, face) in b.faces.iter().enumerate() {
+                let Some(face) = face else {
+                    continue;
+                };
+                let world = turnDir(d, quarters);
+                if b.onBoundary(d) && hidden(world) {
+                    continue;
+                }
+                let mut corners = [Vector3::new(0, 0, 0); 4];
+                let mut uvs = [[0; 2]; 4];
+                for c in 0..4 {
+                    let corner = (120 / 6 * d) + c * 5;
+                    let pick = |i: usize| {
+                        if vertices[corner + i] > 0.0 {
+                            b.to[i]
+                        } else {
+                            b.from[i]
+                        }
+                    };
+                    corners[c] =
+                        origin + Vector3::from(turn([pick(0), pick(1), pick(2)], quarters));
+                    let [u0, v0, u1, v1] = face.uv;
+                    uvs[c] = [
+                        if vertices[corner + 3] > 0.5 { u1 } else { u0 },
+                        if vertices[corner + 4] > 0.5 { v1 } else { v0 },
+                    ];
+                }
+                let layer = face.layer.unwrap_or(def.layers[d]);
+                mesh.pushRaw(world as u32, corners, uvs, layer, [3; 4]);
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+
+/// Seeded improved Perlin noise. Values are roughly in [-1, 1] and only
+/// depend on the seed and the sample position.
+pub struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+        let mut perm = [0u8; 512];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = table[i & 255];
+        }
+        Self { perm }
+    }
+
+    fn hash(&self, i: i32) -> usize {
+        self.perm[(i & 255) as usize] as usize
+    }
+
+    pub fn noise2(&self, x: f64, y: f64) -> f64 {
+        let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+        let (xf, yf) = (x - x.floor(), y - y.floor());
+        let (u, v) = (fade(xf), fade(yf));
+
+        let aa = self.hash(self.hash(xi) as i32 + yi);
+        let ab = self.hash(self.hash(xi) as i32 + yi + 1);
+        let ba = self.hash(self.hash(xi + 1) as i32 + yi);
+        let bb = self.hash(self.hash(xi + 1) as i32 + yi + 1);
+
+        let x1 = lerp(u, grad2(aa, xf, yf), grad2(ba, xf - 1.0, yf));
+        let x2 = lerp(u, grad2(ab, xf, yf - 1.0), grad2(bb, xf - 1.0, yf - 1.0));
+        lerp(v, x1, x2)
+    }
+
+    pub fn noise3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (xf, yf, zf) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+
+        let a = self.hash(xi) as i32 + yi;
+        let aa = self.hash(a) as i32 + zi;
+        let ab = self.hash(a + 1) as i32 + zi;
+        let b = self.hash(xi + 1) as i32 + yi;
+        let ba = self.hash(b) as i32 + zi;
+        let bb = self.hash(b + 1) as i32 + zi;
+
+        let g = |h: i32, dx: f64, dy: f64, dz: f64| grad3(self.hash(h), dx, dy, dz);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, g(aa, xf, yf, zf), g(ba, xf - 1.0, yf, zf)),
+                lerp(u, g(ab, xf, yf - 1.0, zf), g(bb, xf - 1.0, yf - 1.0, zf)),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    g(aa + 1, xf, yf, zf - 1.0),
+                    g(ba + 1, xf - 1.0, yf, zf - 1.0),
+                ),
+                lerp(
+                    u,
+                    g(ab + 1, xf, yf - 1.0, zf - 1.0),
+                    g(bb + 1, xf - 1.0, yf - 1.0, zf - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Fractal (fBm) sum of `octaves` layers, each at double the frequency
+    /// and half the amplitude of the last. Normalised back to about [-1, 1].
+    pub fn fbm2(&self, x: f64, y: f64, octaves: u32) -> f64 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves {
+            sum += amp * self.noise2(x * freq, y * freq);
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / norm
+    }
+
+    pub fn fbm3(&self, x: f64, y: f64, z: f64, octaves: u32) -> f64 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves {
+            sum += amp * self.noise3(x * freq, y * freq, z * freq);
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / norm
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad2(hash: usize, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+fn grad3(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
@@ -0,0 +1,115 @@
+use cgmath::Vector3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+//local
+use crate::block::{BlockId, BlockRegistry, OreDef};
+use crate::generator::chunkSeed;
+use crate::world::{CHUNKSIZE, Chunk, ChunkPos};
+
+// a vein never strays further than this from its centre, so only veins from
+// the 8 neighbouring chunks can reach into a chunk
+const VEINREACH: i32 = 4;
+// keeps ore streams apart from the other stages using chunkSeed
+const ORESALT: u64 = 0x0e5;
+
+// 64-bit FNV-1a, ids follow blocks.toml order so the name is what stays put
+fn nameHash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Decoration stage that scatters every ore from the block registry as
+/// blob-shaped veins. Each ore draws from its own RNG seeded by the world
+/// seed, the ore's name and the chunk a vein starts in, so adding or
+/// reordering blocks doesn't move the others and veins crossing chunk borders
+/// match on both sides.
+pub struct OreDecorator {
+    seed: u64,
+    // (block, salt from its name, placement)
+    ores: Vec<(BlockId, u64, OreDef)>,
+}
+
+impl OreDecorator {
+    pub fn new(seed: u64, registry: &BlockRegistry) -> Self {
+        Self {
+            seed,
+            ores: registry
+                .ores()
+                .map(|(id, ore)| (id, ORESALT ^ nameHash(&registry.get(id).name), ore.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn decorate(&self, pos: ChunkPos, chunk: &mut Chunk) {
+        for (id, salt, ore) in &self.ores {
+            for dx in -1..=1 {
+                for dz in -1..=1 {
+                    let origin = ChunkPos::new(pos.x + dx, pos.z + dz);
+                    self.placeVeins(*id, *salt, ore, origin, pos, chunk);
+                }
+            }
+        }
+    }
+
+    // replays every vein of one ore starting in `origin`, writing the cells inside `pos`
+    fn placeVeins(
+        &self,
+        id: BlockId,
+        salt: u64,
+        ore: &OreDef,
+        origin: ChunkPos,
+        pos: ChunkPos,
+        chunk: &mut Chunk,
+    ) {
+        let mut rng = ChaCha8Rng::seed_from_u64(chunkSeed(self.seed, salt, origin));
+        let height = chunk.height() as i32;
+        let ox = pos.x * CHUNKSIZE as i32;
+        let oz = pos.z * CHUNKSIZE as i32;
+
+        for _ in 0..ore.frequency {
+            let centre = Vector3::new(
+                origin.x * CHUNKSIZE as i32 + rng.random_range(0..CHUNKSIZE as i32),
+                rng.random_range(ore.minY..=ore.maxY),
+                origin.z * CHUNKSIZE as i32 + rng.random_range(0..CHUNKSIZE as i32),
+            );
+
+            // random walk around the centre until the vein has `size` blocks
+            let mut vein = vec![centre];
+            let mut cell = centre;
+            for _ in 0..ore.size * 4 {
+                if vein.len() >= ore.size as usize {
+                    break;
+                }
+                match rng.random_range(0..6) {
+                    0 => cell.x -= 1,
+                    1 => cell.x += 1,
+                    2 => cell.y -= 1,
+                    3 => cell.y += 1,
+                    4 => cell.z -= 1,
+                    _ => cell.z += 1,
+                }
+                let off = cell - centre;
+                if off.x.abs().max(off.y.abs()).max(off.z.abs()) > VEINREACH {
+                    cell = *vein.last().unwrap();
+                } else if !vein.contains(&cell) {
+                    vein.push(cell);
+                }
+            }
+
+            for cell in vein {
+                let (x, z) = (cell.x - ox, cell.z - oz);
+                if (0..CHUNKSIZE as i32).contains(&x)
+                    && (0..CHUNKSIZE as i32).contains(&z)
+                    && (0..height).contains(&cell.y)
+                {
+                    let cord = Vector3::new(x as usize, cell.y as usize, z as usize);
+                    if ore.replaces.contains(&chunk.get(cord)) {
+                        chunk.set(cord, id);
+                    }
+                }
+            }
+        }
+    }
+}
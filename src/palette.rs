@@ -0,0 +1,189 @@
+use std::fmt;
+use std::mem;
+use std::ops::Add;
+
+// bits per entry never go past this, 16 bits is enough for any palette we can index
+const MAXBITS: u32 = 16;
+
+/// Fixed length array of values stored as a palette of distinct values plus
+/// bit packed indices into it. While every entry holds the same value no index
+/// data is allocated at all; the index width grows as new values show up.
+pub struct PalettedContainer<T> {
+    palette: Vec<T>,
+    bits: u32,
+    data: Vec<u64>,
+    len: usize,
+}
+
+impl<T: Copy + PartialEq> PalettedContainer<T> {
+    pub fn new(len: usize, fill: T) -> Self {
+        Self {
+            palette: vec![fill],
+            bits: 0,
+            data: Vec::new(),
+            len,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> T {
+        debug_assert!(
+            index < self.len,
+            "PalettedContainer::get(): index out of range"
+        );
+        if self.bits == 0 {
+            return self.palette[0];
+        }
+        self.palette[self.readIndex(index)]
+    }
+
+    pub fn set(&mut self, index: usize, value: T) {
+        debug_assert!(
+            index < self.len,
+            "PalettedContainer::set(): index out of range"
+        );
+        let paletteIndex = match self.palette.iter().position(|v| *v == value) {
+            Some(i) => i,
+            None => {
+                self.palette.push(value);
+                let needed = bitsFor(self.palette.len());
+                if needed > self.bits {
+                    self.repack(needed);
+                }
+                self.palette.len() - 1
+            }
+        };
+        if self.bits == 0 {
+            // only reachable when writing the single value back
+            return;
+        }
+        self.writeIndex(index, paletteIndex);
+    }
+
+    /// Sets every entry to `value` and frees the index data.
+    pub fn fill(&mut self, value: T) {
+        self.palette.clear();
+        self.palette.push(value);
+        self.bits = 0;
+        self.data = Vec::new();
+    }
+
+    /// True when every entry holds the same value.
+    pub fn isUniform(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn palette(&self) -> &[T] {
+        &self.palette
+    }
+
+    /// Drops palette entries that are no longer referenced and shrinks the
+    /// index width to match, falling back to the single value form if possible.
+    pub fn compact(&mut self) {
+        if self.bits == 0 {
+            return;
+        }
+        let mut used = vec![false; self.palette.len()];
+        for i in 0..self.len {
+            used[self.readIndex(i)] = true;
+        }
+        if used.iter().all(|u| *u) {
+            return;
+        }
+
+        let old = mem::replace(self, Self::new(self.len, self.get(0)));
+        for i in 0..old.len {
+            self.set(i, old.get(i));
+        }
+    }
+
+    pub fn report(&self) -> StorageReport {
+        StorageReport {
+            largestPalette: self.palette.len(),
+            bitsPerEntry: self.bits,
+            bytes: mem::size_of::<Self>()
+                + self.palette.capacity() * mem::size_of::<T>()
+                + self.data.capacity() * mem::size_of::<u64>(),
+            denseBytes: self.len * mem::size_of::<T>(),
+        }
+    }
+
+    fn entriesPerWord(&self) -> usize {
+        (64 / self.bits) as usize
+    }
+
+    fn readIndex(&self, index: usize) -> usize {
+        let perWord = self.entriesPerWord();
+        let word = self.data[index / perWord];
+        let shift = (index % perWord) as u32 * self.bits;
+        ((word >> shift) & ((1u64 << self.bits) - 1)) as usize
+    }
+
+    fn writeIndex(&mut self, index: usize, value: usize) {
+        let perWord = self.entriesPerWord();
+        let shift = (index % perWord) as u32 * self.bits;
+        let mask = ((1u64 << self.bits) - 1) << shift;
+        let word = &mut self.data[index / perWord];
+        *word = (*word & !mask) | ((value as u64) << shift);
+    }
+
+    // rewrites the indices at a new width, entries never straddle two words
+    fn repack(&mut self, bits: u32) {
+        assert!(bits <= MAXBITS, "PalettedContainer: palette too large");
+        let old: Vec<usize> = if self.bits == 0 {
+            Vec::new()
+        } else {
+            (0..self.len).map(|i| self.readIndex(i)).collect()
+        };
+
+        self.bits = bits;
+        let perWord = self.entriesPerWord();
+        self.data = vec![0u64; self.len.div_ceil(perWord)];
+        for (i, v) in old.into_iter().enumerate() {
+            if v != 0 {
+                self.writeIndex(i, v);
+            }
+        }
+    }
+}
+
+fn bitsFor(paletteLen: usize) -> u32 {
+    if paletteLen <= 1 {
+        0
+    } else {
+        usize::BITS - (paletteLen - 1).leading_zeros()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct StorageReport {
+    // these two are the largest of any container summed into the report
+    pub largestPalette: usize,
+    pub bitsPerEntry: u32,
+    pub bytes: usize,
+    // what the same data would take as a flat array
+    pub denseBytes: usize,
+}
+
+impl fmt::Display for StorageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes (largest palette {}, up to {} bits/entry, dense {} bytes)",
+            self.bytes, self.largestPalette, self.bitsPerEntry, self.denseBytes
+        )
+    }
+}
+
+// summing reports gives the footprint of several containers, e.g. all sections of a chunk
+impl Add for StorageReport {
+    type Output = StorageReport;
+
+    fn add(self, other: StorageReport) -> StorageReport {
+        StorageReport {
+            largestPalette: self.largestPalette.max(other.largestPalette),
+            bitsPerEntry: self.bitsPerEntry.max(other.bitsPerEntry),
+            bytes: self.bytes + other.bytes,
+            denseBytes: self.denseBytes + other.denseBytes,
+        }
+    }
+}
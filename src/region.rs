@@ -0,0 +1,307 @@
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+//local
+use crate::biome::{BIOMES, BiomeId};
+use crate::block::{Block, BlockId, BlockRegistry, BlockState};
+use crate::world::{CHUNKSIZE, Chunk, ChunkPos, SECTIONSIZE};
+use cgmath::Vector3;
+
+// chunks per region side, a region file holds REGIONSIZE x REGIONSIZE chunks
+const REGIONSIZE: i32 = 32;
+// chunk data is stored in whole sectors so it can be rewritten in place
+const SECTOR: u64 = 4096;
+// per chunk: u32 first sector, u32 byte length (0 = not stored)
+const HEADERBYTES: u64 = (REGIONSIZE * REGIONSIZE) as u64 * 8;
+const HEADERSECTORS: u64 = HEADERBYTES.div_ceil(SECTOR);
+// 1: blocks only, 2: + biomes
+const FORMATVERSION: u8 = 2;
+
+/// Directory of region files, each holding the zlib compressed chunks of a
+/// 32x32 chunk area behind an offset table.
+pub struct RegionStore {
+    dir: PathBuf,
+}
+
+impl RegionStore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn regionPath(&self, pos: ChunkPos) -> (PathBuf, u64) {
+        let rx = pos.x.div_euclid(REGIONSIZE);
+        let rz = pos.z.div_euclid(REGIONSIZE);
+        let slot = pos.x.rem_euclid(REGIONSIZE) + pos.z.rem_euclid(REGIONSIZE) * REGIONSIZE;
+        (
+            self.dir.join(format!("r.{}.{}.region", rx, rz)),
+            slot as u64,
+        )
+    }
+
+    fn readEntry(file: &mut File, slot: u64) -> io::Result<(u32, u32)> {
+        let mut entry = [0u8; 8];
+        file.seek(SeekFrom::Start(slot * 8))?;
+        file.read_exact(&mut entry)?;
+        Ok((
+            u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+            u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+        ))
+    }
+
+    // which sectors of the file hold the header or a chunk other than the one in `skip`
+    fn usedSectors(file: &mut File, skip: u64) -> io::Result<Vec<bool>> {
+        let mut used = vec![false; file.metadata()?.len().div_ceil(SECTOR) as usize];
+        used[..HEADERSECTORS as usize].fill(true);
+        let mut header = vec![0u8; HEADERBYTES as usize];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        for (slot, entry) in header.chunks_exact(8).enumerate() {
+            let sector = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+            let len = u32::from_le_bytes(entry[4..8].try_into().unwrap()) as usize;
+            if slot as u64 == skip || len == 0 {
+                continue;
+            }
+            let end = (sector + len.div_ceil(SECTOR as usize)).min(used.len());
+            used[sector.min(end)..end].fill(true);
+        }
+        Ok(used)
+    }
+
+    /// Fills `chunk` from disk. Ok(false) means the chunk was never saved.
+    pub fn load(&self, chunk: &mut Chunk, registry: &BlockRegistry) -> io::Result<bool> {
+        let (path, slot) = self.regionPath(chunk.pos());
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let (sector, len) = RegionStore::readEntry(&mut file, slot)?;
+        if len == 0 {
+            return Ok(false);
+        }
+
+        let mut compressed = vec![0u8; len as usize];
+        file.seek(SeekFrom::Start(sector as u64 * SECTOR))?;
+        file.read_exact(&mut compressed)?;
+        let mut data = Vec::new();
+        ZlibDecoder::new(&compressed[..]).read_to_end(&mut data)?;
+        decodeChunk(chunk, &data, registry)?;
+        Ok(true)
+    }
+
+    pub fn save(&self, chunk: &Chunk, registry: &BlockRegistry) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&encodeChunk(chunk, registry))?;
+        let compressed = encoder.finish()?;
+
+        fs::create_dir_all(&self.dir)?;
+        let (path, slot) = self.regionPath(chunk.pos());
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if file.metadata()?.len() < HEADERSECTORS * SECTOR {
+            file.set_len(HEADERSECTORS * SECTOR)?;
+        }
+
+        // first run of sectors no other chunk uses that's big enough, which can be the
+        // chunk's own old ones, otherwise append
+        let needed = (compressed.len() as u64).div_ceil(SECTOR);
+        let used = RegionStore::usedSectors(&mut file, slot)?;
+        let sector = (HEADERSECTORS..=used.len() as u64)
+            .find(|&start| {
+                (start..start + needed)
+                    .take_while(|s| *s < used.len() as u64)
+                    .all(|s| !used[s as usize])
+            })
+            .unwrap();
+
+        file.seek(SeekFrom::Start(sector * SECTOR))?;
+        file.write_all(&compressed)?;
+        let padded = (sector + needed) * SECTOR;
+        if file.metadata()?.len() < padded {
+            file.set_len(padded)?;
+        }
+
+        let mut entry = [0u8; 8];
+        entry[0..4].copy_from_slice(&(sector as u32).to_le_bytes());
+        entry[4..8].copy_from_slice(&(compressed.len() as u32).to_le_bytes());
+        file.seek(SeekFrom::Start(slot * 8))?;
+        file.write_all(&entry)?;
+        Ok(())
+    }
+}
+
+// uncompressed chunk layout, all little endian:
+//   u8 version, u16 section count,
+//   u16 name count, then per name u16 length + utf8 bytes (ids are per save, not per registry)
+//   per section: u16 palette length, per entry u16 name index + u16 state,
+//   then 16^3 indices into the palette (u8 if the palette fits, else u16), omitted for 1 entry,
+//   then (version 2) one u8 biome per column, x major
+fn encodeChunk(chunk: &Chunk, registry: &BlockRegistry) -> Vec<u8> {
+    let sections = chunk.height() / SECTIONSIZE;
+    let mut names: Vec<BlockId> = Vec::new();
+    let mut body = Vec::new();
+
+    for sy in 0..sections {
+        let mut palette: Vec<Block> = Vec::new();
+        let mut indices: Vec<u16> = Vec::with_capacity(SECTIONSIZE * SECTIONSIZE * SECTIONSIZE);
+        for x in 0..SECTIONSIZE {
+            for y in 0..SECTIONSIZE {
+                for z in 0..SECTIONSIZE {
+                    let block = chunk.getBlock(Vector3::new(x, sy * SECTIONSIZE + y, z));
+                    let i = match palette.iter().position(|b| *b == block) {
+                        Some(i) => i,
+                        None => {
+                            palette.push(block);
+                            palette.len() - 1
+                        }
+                    };
+                    indices.push(i as u16);
+                }
+            }
+        }
+
+        body.extend((palette.len() as u16).to_le_bytes());
+        for block in &palette {
+            let name = match names.iter().position(|n| *n == block.id) {
+                Some(n) => n,
+                None => {
+                    names.push(block.id);
+                    names.len() - 1
+                }
+            };
+            body.extend((name as u16).to_le_bytes());
+            body.extend(block.state.0.to_le_bytes());
+        }
+        if palette.len() > 1 {
+            for i in indices {
+                if palette.len() <= 256 {
+                    body.push(i as u8);
+                } else {
+                    body.extend(i.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    let mut out = vec![FORMATVERSION];
+    out.extend((sections as u16).to_le_bytes());
+    out.extend((names.len() as u16).to_le_bytes());
+    for id in names {
+        let name = registry.get(id).name.as_bytes();
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend(name);
+    }
+    out.extend(body);
+    for x in 0..CHUNKSIZE {
+        for z in 0..CHUNKSIZE {
+            out.push(chunk.biome(x, z).0);
+        }
+    }
+    out
+}
+
+struct Reader<'d> {
+    data: &'d [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, n: usize) -> io::Result<&[u8]> {
+        if self.at + n > self.data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunk data is truncated",
+            ));
+        }
+        self.at += n;
+        Ok(&self.data[self.at - n..self.at])
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+}
+
+fn decodeChunk(chunk: &mut Chunk, data: &[u8], registry: &BlockRegistry) -> io::Result<()> {
+    let bad = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let mut r = Reader { data, at: 0 };
+
+    let version = r.u8()?;
+    if version == 0 || version > FORMATVERSION {
+        return Err(bad("unknown chunk format version"));
+    }
+    let sections = r.u16()? as usize;
+    let nameCount = r.u16()? as usize;
+    let mut ids = Vec::with_capacity(nameCount);
+    for _ in 0..nameCount {
+        let len = r.u16()? as usize;
+        let name = String::from_utf8_lossy(r.bytes(len)?).into_owned();
+        // blocks removed from the definitions since the save turn into air
+        ids.push(registry.id(&name).unwrap_or_else(|| {
+            println!("region: unknown block '{}' in saved chunk, using air", name);
+            BlockId::AIR
+        }));
+    }
+
+    // a save from a taller world keeps what fits, a shorter one leaves air on top
+    let keep = sections.min(chunk.height() / SECTIONSIZE);
+    for sy in 0..sections {
+        let paletteLen = r.u16()? as usize;
+        let mut palette = Vec::with_capacity(paletteLen);
+        for _ in 0..paletteLen {
+            let name = r.u16()? as usize;
+            let state = BlockState(r.u16()?);
+            let id = *ids
+                .get(name)
+                .ok_or_else(|| bad("palette names a missing block"))?;
+            palette.push(Block::new(id, state));
+        }
+        if palette.is_empty() {
+            return Err(bad("section with an empty palette"));
+        }
+
+        let wide = palette.len() > 256;
+        for x in 0..SECTIONSIZE {
+            for y in 0..SECTIONSIZE {
+                for z in 0..SECTIONSIZE {
+                    let i = match (palette.len(), wide) {
+                        (1, _) => 0,
+                        (_, false) => r.u8()? as usize,
+                        (_, true) => r.u16()? as usize,
+                    };
+                    let block = *palette.get(i).ok_or_else(|| bad("index outside palette"))?;
+                    if sy < keep && block != Block::AIR {
+                        chunk.setBlock(Vector3::new(x, sy * SECTIONSIZE + y, z), block);
+                    }
+                }
+            }
+        }
+    }
+
+    if version >= 2 {
+        for x in 0..CHUNKSIZE {
+            for z in 0..CHUNKSIZE {
+                let biome = r.u8()?;
+                if biome as usize >= BIOMES.len() {
+                    return Err(bad("unknown biome"));
+                }
+                chunk.setBiome(x, z, BiomeId(biome));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,386 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+use gl::types::*;
+use image::GenericImage;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::Path;
+use std::{mem, ptr};
+
+//local
+use crate::block::{BlockRegistry, RenderClass};
+use crate::shader::Shader;
+use crate::world::{
+    ChunkPos, IVec3, MeshData, MeshUpdate, SECTIONSIZE, SectionMesh, VERTEXWORDS, World,
+};
+
+pub fn make_texture_array(tex_paths: &[&str], shader: &Shader) -> u32 {
+    let layer_count = tex_paths.len() as i32;
+    let mut tex_array = 0;
+    unsafe {
+        gl::GenTextures(1, &mut tex_array);
+        gl::BindTexture(gl::TEXTURE_2D_ARRAY, tex_array);
+        // Allocate storage: width, height and layer_count
+        // assuming all images are the same size WxH:
+        let img0 = image::open(Path::new(tex_paths[0])).unwrap();
+        let (w, h) = (img0.width() as i32, img0.height() as i32);
+        gl::TexStorage3D(
+            gl::TEXTURE_2D_ARRAY,
+            1,         // mip levels (or more if you want mips)
+            gl::RGBA8, // internal format
+            w,
+            h,
+            layer_count,
+        );
+        // Upload each layer
+        for (layer, path) in tex_paths.iter().enumerate() {
+            let img = image::open(Path::new(path)).expect("Failed to load layer");
+            let data = img.to_rgba().into_raw();
+            gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0, // mip level
+                0,
+                0,
+                layer as i32, // x, y, layer offset
+                w,
+                h,
+                1, // size in x,y,1 layer
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const _,
+            );
+        }
+        // Filtering & wrapping
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_MIN_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_MAG_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+
+        // Bind to our shader once
+        shader.useProgram();
+        shader.setInt(c"texture1", 0); // texture unit 0
+    }
+    tex_array
+}
+
+// GL side of one section mesh
+struct GpuMesh {
+    VAO: u32,
+    VBO: u32,
+    EBO: u32,
+    indexCount: i32,
+}
+
+impl GpuMesh {
+    fn new() -> Self {
+        let (mut VBO, mut VAO, mut EBO) = (0, 0, 0);
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut VAO);
+            gl::GenBuffers(1, &mut VBO);
+            gl::GenBuffers(1, &mut EBO);
+
+            gl::BindVertexArray(VAO);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, VBO);
+
+            // integer attributes, the packed words reach the shader as they are
+            let stride = (VERTEXWORDS * mem::size_of::<GLuint>()) as GLsizei;
+            // position, face and ao word
+            gl::VertexAttribIPointer(0, 1, gl::UNSIGNED_INT, stride, ptr::null());
+            gl::EnableVertexAttribArray(0);
+            // uv and texture layer word
+            gl::VertexAttribIPointer(
+                1,
+                1,
+                gl::UNSIGNED_INT,
+                stride,
+                mem::size_of::<GLuint>() as *const c_void,
+            );
+            gl::EnableVertexAttribArray(1);
+        }
+
+        Self {
+            VAO,
+            VBO,
+            EBO,
+            indexCount: 0,
+        }
+    }
+
+    fn upload(&mut self, data: &MeshData) {
+        self.indexCount = data.indices.len() as i32;
+        unsafe {
+            gl::BindVertexArray(self.VAO);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.VBO);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (data.vertices.len() * mem::size_of::<GLuint>()) as GLsizeiptr,
+                &data.vertices[0] as *const u32 as *const c_void,
+                gl::STATIC_DRAW,
+            );
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.EBO);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                (data.indices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                &data.indices[0] as *const u32 as *const c_void,
+                gl::STATIC_DRAW,
+            );
+        }
+    }
+
+    // same number of indices as uploaded, in a different order
+    fn updateIndices(&self, indices: &[u32]) {
+        unsafe {
+            // the element buffer binding belongs to the VAO
+            gl::BindVertexArray(self.VAO);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.EBO);
+            gl::BufferSubData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                0,
+                mem::size_of_val(indices) as GLsizeiptr,
+                indices.as_ptr() as *const c_void,
+            );
+        }
+    }
+
+    fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.VAO);
+            gl::DrawElements(
+                gl::TRIANGLES,
+                self.indexCount,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.VAO);
+            gl::DeleteBuffers(1, &self.VBO);
+            gl::DeleteBuffers(1, &self.EBO);
+        }
+    }
+}
+
+// GL side of one section: a mesh per RenderClass and the translucent draw order
+#[derive(Default)]
+struct SectionGpu {
+    // one per RenderClass, None while that class has nothing to draw
+    meshes: [Option<GpuMesh>; 3],
+    // centre and indices of each translucent quad, re-sorted as the camera moves
+    translucentQuads: Vec<(Vector3<f32>, [u32; 6])>,
+    // camera block the translucent quads were last sorted for
+    sortedFrom: Option<IVec3>,
+}
+
+/// Owns everything on the GPU for drawing a World: the chunk shader, the
+/// block texture array and the uploaded section meshes. The World meshes
+/// chunks into plain MeshData, sync() picks those up.
+pub struct WorldRenderer {
+    shader: Shader,
+    texture: u32,
+    // uploaded sections per chunk, indexed by section, grown as meshes come in
+    chunks: HashMap<ChunkPos, Vec<SectionGpu>>,
+}
+
+impl WorldRenderer {
+    pub fn new(shader: Shader, registry: &BlockRegistry) -> Self {
+        let texture = make_texture_array(&registry.texturePaths(), &shader);
+        Self {
+            shader,
+            texture,
+            chunks: HashMap::new(),
+        }
+    }
+
+    // uploads the meshes the world made since the last call, drops those of unloaded
+    // chunks and re-sorts translucent faces for the world's camera. call once per
+    // frame after World::update
+    pub fn sync(&mut self, world: &mut World) {
+        for update in world.takeMeshUpdates() {
+            match update {
+                MeshUpdate::Meshed(pos, meshes) => {
+                    let sections = self.chunks.entry(pos).or_default();
+                    for (sy, data) in meshes {
+                        if sections.len() <= sy {
+                            sections.resize_with(sy + 1, SectionGpu::default);
+                        }
+                        sections[sy].upload(data);
+                    }
+                }
+                // SectionGpu's GpuMeshes free their GL buffers on drop
+                MeshUpdate::Unloaded(pos) => {
+                    self.chunks.remove(&pos);
+                }
+            }
+        }
+
+        let camera = world.camera();
+        for (pos, sections) in &mut self.chunks {
+            for (sy, section) in sections.iter_mut().enumerate() {
+                section.sortTranslucent(*pos, sy, camera);
+            }
+        }
+    }
+
+    // quads currently uploaded, solid and fluid, for comparing meshers
+    pub fn quadCount(&self) -> usize {
+        self.chunks
+            .values()
+            .flatten()
+            .flat_map(|s| &s.meshes)
+            .flatten()
+            .map(|m| m.indexCount as usize / 6)
+            .sum()
+    }
+
+    pub fn render(&self, world: &World, proj: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let visible: Vec<(ChunkPos, &Vec<SectionGpu>)> = self
+            .chunks
+            .iter()
+            .filter(|(pos, _)| world.isDrawn(**pos))
+            .map(|(pos, sections)| (*pos, sections))
+            .collect();
+        unsafe {
+            self.shader.useProgram();
+            self.shader.setMat4(c"projection", proj);
+            self.shader.setMat4(c"view", view);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.texture);
+
+            self.shader.setFloat(c"alphaCutoff", 0.0);
+        }
+        for (pos, sections) in &visible {
+            self.drawSections(*pos, sections, RenderClass::Opaque, 0..sections.len());
+        }
+
+        // cutout pixels are either drawn or discarded, so they need no blending or sorting
+        unsafe { self.shader.setFloat(c"alphaCutoff", 0.5) };
+        for (pos, sections) in &visible {
+            self.drawSections(*pos, sections, RenderClass::Cutout, 0..sections.len());
+        }
+
+        // translucent faces blend over whatever is behind them, so they go last and
+        // furthest first, without hiding anything from later translucent faces
+        let camera = world.camera();
+        let centre = |pos: &ChunkPos| {
+            let dx = pos.x as f32 * 16.0 + 8.0 - camera.x;
+            let dz = pos.z as f32 * 16.0 + 8.0 - camera.z;
+            dx * dx + dz * dz
+        };
+        let mut visible = visible;
+        visible.sort_by(|a, b| centre(&b.0).total_cmp(&centre(&a.0)));
+        unsafe {
+            self.shader.setFloat(c"alphaCutoff", 0.0);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        }
+        for (pos, sections) in &visible {
+            // furthest section first within the chunk too
+            let distance = |sy: usize| {
+                let centre = Point3::new(
+                    pos.x as f32 * 16.0 + 8.0,
+                    (sy * SECTIONSIZE + SECTIONSIZE / 2) as f32,
+                    pos.z as f32 * 16.0 + 8.0,
+                );
+                (centre - camera).magnitude2()
+            };
+            let mut order: Vec<usize> = (0..sections.len()).collect();
+            order.sort_by(|a, b| distance(*b).total_cmp(&distance(*a)));
+            self.drawSections(*pos, sections, RenderClass::Translucent, order);
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    // one RenderClass of a chunk's sections, in `order`
+    fn drawSections(
+        &self,
+        pos: ChunkPos,
+        sections: &[SectionGpu],
+        class: RenderClass,
+        order: impl IntoIterator<Item = usize>,
+    ) {
+        for sy in order {
+            let Some(mesh) = &sections[sy].meshes[class as usize] else {
+                continue;
+            };
+            // vertices sit on block corners, so block (x, y, z) covers [x, x + 1) like
+            // worldToLoc expects
+            let model = Matrix4::<f32>::from_translation(Vector3 {
+                x: pos.x as f32 * 16.0,
+                y: (sy * SECTIONSIZE) as f32,
+                z: pos.z as f32 * 16.0,
+            });
+            unsafe { self.shader.setMat4(c"model", &model) };
+            mesh.draw();
+        }
+    }
+}
+
+impl SectionGpu {
+    fn upload(&mut self, data: SectionMesh) {
+        self.translucentQuads = data.translucent.quads();
+        self.sortedFrom = None;
+        // empty meshes keep no GL objects at all
+        for (slot, data) in self
+            .meshes
+            .iter_mut()
+            .zip([data.opaque, data.cutout, data.translucent])
+        {
+            if data.indices.is_empty() {
+                *slot = None;
+            } else {
+                slot.get_or_insert_with(GpuMesh::new).upload(&data);
+            }
+        }
+    }
+
+    // reorders the translucent quads of section `sy` of the chunk at `pos` furthest
+    // from `camera` first, once the camera has moved into another block
+    fn sortTranslucent(&mut self, pos: ChunkPos, sy: usize, camera: Point3<f32>) {
+        let Some(mesh) = &self.meshes[RenderClass::Translucent as usize] else {
+            return;
+        };
+        let eye = Vector3::new(
+            camera.x.floor() as i32,
+            camera.y.floor() as i32,
+            camera.z.floor() as i32,
+        );
+        if self.sortedFrom == Some(eye) {
+            return;
+        }
+        let local = Vector3::new(
+            camera.x - pos.x as f32 * 16.0,
+            camera.y - (sy * SECTIONSIZE) as f32,
+            camera.z - pos.z as f32 * 16.0,
+        );
+        let distance = |centre: &Vector3<f32>| (centre - local).magnitude2();
+        self.translucentQuads
+            .sort_by(|a, b| distance(&b.0).total_cmp(&distance(&a.0)));
+        let indices: Vec<u32> = self
+            .translucentQuads
+            .iter()
+            .flat_map(|(_, quad)| *quad)
+            .collect();
+        mesh.updateIndices(&indices);
+        self.sortedFrom = Some(eye);
+    }
+}
@@ -18,7 +18,8 @@ pub struct Shader {
 
 /// NOTE: mixture of `shader_s.h` and `shader_m.h` (the latter just contains
 /// a few more setters for uniforms)
-#[allow(dead_code)]
+// the unsafe setters only need the GL context the shader was made in to be current
+#[allow(dead_code, clippy::missing_safety_doc)]
 impl Shader {
     pub fn new(vertexPath: &str, fragmentPath: &str) -> Shader {
         let mut shader = Shader { ID: 0 };
@@ -123,8 +124,7 @@ impl Shader {
     unsafe fn checkCompileErrors(&self, shader: u32, type_: &str) {
         unsafe {
             let mut success = gl::FALSE as GLint;
-            let mut infoLog = Vec::with_capacity(1024);
-            infoLog.set_len(1024 - 1); // subtract 1 to skip the trailing null character
+            let mut infoLog = vec![0u8; 1024];
             if type_ != "PROGRAM" {
                 gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
                 if success != gl::TRUE as GLint {
@@ -4,12 +4,18 @@ out vec4 FragColor;
 
 in vec2 TexCoord;
 in float TexIndex;
+in float Shade;
 
 // texture samplers
 uniform sampler2DArray texture1;
+// cutout blocks drop pixels below this alpha, 0 keeps everything
+uniform float alphaCutoff;
 
 void main()
 {
 	FragColor = texture(texture1, vec3(TexCoord, TexIndex));
+	if (FragColor.a < alphaCutoff)
+		discard;
+	FragColor.rgb *= Shade;
 }
 
@@ -1,19 +1,28 @@
 #version 330 core
-layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec2 aTexCoord;
-layout (location = 2) in float aTexIndex;
+// packed chunk vertex, see packVertex in world.rs
+layout (location = 0) in uint aPos;
+layout (location = 1) in uint aTex;
 
 out vec2 TexCoord;
 out float TexIndex;
+out float Shade;
 
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 
+// vertex positions and uvs come in 1/16ths of a block
+const float SUBDIV = 16.0;
+
 void main()
 {
-    gl_Position = projection * view * model * vec4(aPos, 1.0f);
-    TexCoord = vec2(aTexCoord.x, 1.0 - aTexCoord.y);
-    TexIndex = aTexIndex;
+    vec3 pos = vec3(aPos & 511u, (aPos >> 9) & 511u, (aPos >> 18) & 511u) / SUBDIV;
+    vec2 uv = vec2(aTex & 511u, (aTex >> 9) & 511u) / SUBDIV;
+
+    gl_Position = projection * view * model * vec4(pos, 1.0f);
+    TexCoord = vec2(uv.x, 1.0 - uv.y);
+    TexIndex = float(aTex >> 18);
+    // ambient occlusion, 0 (boxed in) to 3 (open)
+    Shade = 0.4 + 0.2 * float((aPos >> 30) & 3u);
 }
 
@@ -1,24 +1,38 @@
-use cgmath::{Matrix4, Point1, Point2, Point3, Vector2};
-use cgmath::{SquareMatrix, Vector3};
+use cgmath::{ElementWise, Point3, Vector3};
 use crossbeam::{channel, thread};
-use gl::types::*;
-use std::collections::HashMap;
-use std::ffi::c_void;
-use std::ops::Deref;
-use std::sync::Arc;
-use std::sync::Mutex;
-use std::{mem, ptr};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
 
 //local
-use crate::Shader;
-use crate::common::make_texture_array;
+use crate::biome::BiomeId;
+use crate::block::{Block, BlockId, BlockRegistry, BlockState, RenderClass};
+use crate::fluid::{self, FluidTicks};
+use crate::generator::{PendingWrites, WorldGenerator};
+use crate::lod::{LODLEVELS, LodGrid, LodPick};
+use crate::mesher::{Face, FaceGrid, Mesher};
+use crate::palette::{PalettedContainer, StorageReport};
+use crate::region::RegionStore;
 
 //settings
-const CHUNKSIZE: usize = 16;
-const CHUNKHIEGHT: usize = 128;
+pub const CHUNKSIZE: usize = 16;
+pub const SECTIONSIZE: usize = 16;
+// default column height, any multiple of SECTIONSIZE works
+const WORLDHEIGHT: usize = 128;
 const RENDERDISTANCE: usize = 3;
+// rings of chunks past the render distance drawn from downsampled blocks
+const LODDISTANCE: usize = 6;
 const THREADS: usize = 8;
-const vertices: [f32; 120] = [
+pub const BLOCKDEFS: &str = "src/blocks.toml";
+// region files of edited chunks
+const SAVEDIR: &str = "saves/world";
+// how many chunks get moved on through generation per call to update()
+const GENPERFRAME: usize = THREADS * 2;
+// how many section meshes update() builds per frame
+const REMESHBUDGET: usize = 64;
+// seconds per World::tick()
+pub const TICKLENGTH: f32 = 0.05;
+pub const vertices: [f32; 120] = [
     // back  (‑Z)
     -0.5, -0.5, -0.5, 0.0, 0.0, // 0
     0.5, -0.5, -0.5, 1.0, 0.0, // 1
@@ -51,7 +65,7 @@ const vertices: [f32; 120] = [
     -0.5, 0.5, 0.5, 0.0, 0.0, //23
 ];
 
-const DIRS: [[i16; 3]; 6] = [
+pub const DIRS: [[i16; 3]; 6] = [
     [0, 0, -1], //back
     [0, 0, 1],  //front
     [-1, 0, 0], //left
@@ -60,85 +74,582 @@ const DIRS: [[i16; 3]; 6] = [
     [0, 1, 0],  //up
 ];
 
+// the world axes (0 x, 1 y, 2 z) a face's texture u and v run along, per DIRS entry
+pub const FACEAXES: [(usize, usize); 6] = [(0, 1), (0, 1), (2, 1), (2, 1), (0, 2), (0, 2)];
+
+// integer world-space block coordinates
+pub type IVec3 = Vector3<i32>;
+
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
 pub struct ChunkPos {
-    x: i32,
-    z: i32,
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    // chebyshev distance in chunks, so the loaded area is a square around the camera
+    pub fn distance(&self, other: ChunkPos) -> i32 {
+        (self.x - other.x).abs().max((self.z - other.z).abs())
+    }
+
+    pub fn neighbours(&self) -> [ChunkPos; 4] {
+        [
+            ChunkPos::new(self.x - 1, self.z),
+            ChunkPos::new(self.x + 1, self.z),
+            ChunkPos::new(self.x, self.z - 1),
+            ChunkPos::new(self.x, self.z + 1),
+        ]
+    }
+
+    // the chunk next to this one in horizontal DIRS direction `d`
+    pub fn step(&self, d: usize) -> ChunkPos {
+        ChunkPos::new(self.x + DIRS[d][0] as i32, self.z + DIRS[d][2] as i32)
+    }
+
+    // the 8 chunks around this one, diagonals included
+    pub fn surrounding(&self) -> [ChunkPos; 8] {
+        [
+            ChunkPos::new(self.x - 1, self.z - 1),
+            ChunkPos::new(self.x, self.z - 1),
+            ChunkPos::new(self.x + 1, self.z - 1),
+            ChunkPos::new(self.x - 1, self.z),
+            ChunkPos::new(self.x + 1, self.z),
+            ChunkPos::new(self.x - 1, self.z + 1),
+            ChunkPos::new(self.x, self.z + 1),
+            ChunkPos::new(self.x + 1, self.z + 1),
+        ]
+    }
 }
 
-pub struct World<'a> {
-    chunks: HashMap<ChunkPos, Chunk<'a>>,
+/// How far a chunk has got through generation. Each step needs the one
+/// before it, and some also need every chunk around to be far enough along.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ChunkStatus {
+    Empty,
+    Terrain,
+    Carved,
+    // own features placed, the blocks they put in neighbours kept for them
+    Decorated,
+    // blocks spilled by the surrounding chunks applied, so the blocks are final. no
+    // lighting is worked out here (AO comes from the mesher), it's only the spill step
+    Lit,
+    Meshed,
 }
 
-impl<'a> World<'a> {
-    pub fn new(shader: &'a Shader) -> Self {
-        let texture = make_texture_array(
-            &["src/textures/txDirt.png", "src/textures/txGrass.png"],
-            shader,
-        );
-        let mut chunks = HashMap::new();
-        for x in 0..RENDERDISTANCE as i32 {
-            for z in 0..RENDERDISTANCE as i32 {
-                let pos = ChunkPos {
-                    x: x - RENDERDISTANCE as i32 / 2,
-                    z: z - RENDERDISTANCE as i32 / 2,
-                };
-                chunks.insert(pos, Chunk::new(shader, pos, texture));
+impl ChunkStatus {
+    fn next(self) -> Option<ChunkStatus> {
+        match self {
+            ChunkStatus::Empty => Some(ChunkStatus::Terrain),
+            ChunkStatus::Terrain => Some(ChunkStatus::Carved),
+            ChunkStatus::Carved => Some(ChunkStatus::Decorated),
+            ChunkStatus::Decorated => Some(ChunkStatus::Lit),
+            ChunkStatus::Lit => Some(ChunkStatus::Meshed),
+            ChunkStatus::Meshed => None,
+        }
+    }
+
+    // what the surrounding chunks have to be at before a chunk can reach this status.
+    // Lit waits for every feature that could spill in, Meshed for final neighbour blocks
+    fn neighbourPrereq(self) -> Option<ChunkStatus> {
+        match self {
+            ChunkStatus::Lit => Some(ChunkStatus::Decorated),
+            ChunkStatus::Meshed => Some(ChunkStatus::Lit),
+            _ => None,
+        }
+    }
+}
+
+pub struct World {
+    chunks: HashMap<ChunkPos, Chunk>,
+    // chunk the camera was in last update, None forces a full reload
+    center: Option<ChunkPos>,
+    renderDistance: i32,
+    // LOD rings past renderDistance, split evenly between the levels
+    lodDistance: i32,
+    lodPick: LodPick,
+    // loaded chunks with at least one section whose mesh is out of date
+    dirty: HashSet<ChunkPos>,
+    // column height in blocks, a multiple of SECTIONSIZE
+    height: usize,
+    registry: BlockRegistry,
+    store: RegionStore,
+    generator: Box<dyn WorldGenerator>,
+    fluidTicks: FluidTicks,
+    mesher: Mesher,
+    // as of the last update(), the renderer sorts translucent faces from here
+    camera: Point3<f32>,
+    // finished meshes and unloads, waiting for the renderer to pick them up
+    meshUpdates: Vec<MeshUpdate>,
+}
+
+/// What changed about the chunk meshes since the renderer last called
+/// World::takeMeshUpdates, in the order it happened.
+pub enum MeshUpdate {
+    // fresh meshes for some of the chunk's sections, (section index, mesh) pairs
+    Meshed(ChunkPos, Vec<(usize, SectionMesh)>),
+    // the chunk was dropped, its meshes can go too
+    Unloaded(ChunkPos),
+}
+
+impl World {
+    pub fn new(registry: BlockRegistry, generator: Box<dyn WorldGenerator>) -> Self {
+        World::withHeight(registry, generator, WORLDHEIGHT)
+    }
+
+    pub fn withHeight(
+        registry: BlockRegistry,
+        generator: Box<dyn WorldGenerator>,
+        height: usize,
+    ) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            center: None,
+            renderDistance: RENDERDISTANCE as i32 / 2,
+            lodDistance: LODDISTANCE as i32,
+            lodPick: LodPick::default(),
+            dirty: HashSet::new(),
+            height,
+            registry,
+            store: RegionStore::new(SAVEDIR),
+            generator,
+            fluidTicks: FluidTicks::default(),
+            mesher: Mesher::default(),
+            camera: Point3::new(0.0, 0.0, 0.0),
+            meshUpdates: Vec::new(),
+        }
+    }
+
+    pub fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&pos)
+    }
+
+    // where edited chunks are saved to and loaded from, saves/world unless set.
+    // chunks already loaded keep what they were loaded with
+    pub fn setSaveDir(&mut self, dir: impl AsRef<Path>) {
+        self.store = RegionStore::new(dir);
+    }
+
+    pub fn camera(&self) -> Point3<f32> {
+        self.camera
+    }
+
+    // everything meshed or unloaded since the last call, oldest first
+    pub fn takeMeshUpdates(&mut self) -> Vec<MeshUpdate> {
+        std::mem::take(&mut self.meshUpdates)
+    }
+
+    // whether the meshes of the chunk at `pos` should be drawn. chunks outside a shrunk
+    // render distance keep their meshes until unloaded, ones changing level show the
+    // old mesh until the new one is in
+    pub fn isDrawn(&self, pos: ChunkPos) -> bool {
+        self.detailLevel(pos).is_some()
+            && self
+                .chunks
+                .get(&pos)
+                .is_some_and(|chunk| chunk.meshLevel.is_some())
+    }
+
+    pub fn renderDistance(&self) -> i32 {
+        self.renderDistance
+    }
+
+    // radius in chunks around the camera chunk, takes effect on the next update()
+    pub fn setRenderDistance(&mut self, distance: i32) {
+        let distance = distance.max(0);
+        if distance != self.renderDistance {
+            self.renderDistance = distance;
+            self.center = None;
+        }
+    }
+
+    pub fn lodDistance(&self) -> i32 {
+        self.lodDistance
+    }
+
+    // LOD rings past the render distance, 0 turns LOD off. takes effect on the next update()
+    pub fn setLodDistance(&mut self, distance: i32) {
+        let distance = distance.max(0);
+        if distance != self.lodDistance {
+            self.lodDistance = distance;
+            self.center = None;
+        }
+    }
+
+    pub fn lodPick(&self) -> LodPick {
+        self.lodPick
+    }
+
+    pub fn setLodPick(&mut self, pick: LodPick) {
+        if pick == self.lodPick {
+            return;
+        }
+        self.lodPick = pick;
+        self.remeshWhere(|chunk| chunk.meshLevel.is_some_and(|l| l > 0));
+    }
+
+    pub fn mesher(&self) -> Mesher {
+        self.mesher
+    }
+
+    // switching remeshes every chunk that already has a mesh
+    pub fn setMesher(&mut self, mesher: Mesher) {
+        if mesher == self.mesher {
+            return;
+        }
+        self.mesher = mesher;
+        self.remeshWhere(|chunk| chunk.meshLevel.is_some());
+    }
+
+    fn remeshWhere(&mut self, pred: impl Fn(&Chunk) -> bool) {
+        for (pos, chunk) in &mut self.chunks {
+            if pred(chunk) {
+                chunk.markAllDirty();
+                self.dirty.insert(*pos);
             }
         }
-        Self { chunks }
     }
 
-    fn fillChunk(chunk: &mut Chunk) {
-        for x in 0..16 {
-            for y in 0..CHUNKHIEGHT {
-                for z in 0..16 {
-                    if y >= 120 {
-                        chunk.set(Vector3 { x, y, z }, BlockId::Grass);
-                    } else {
-                        chunk.set(Vector3 { x, y, z }, BlockId::Dirt);
-                    }
-                }
+    /// How a chunk at `pos` is drawn: 0 is full detail inside the render
+    /// distance, 1 to LODLEVELS are cells 2^level blocks across in the rings
+    /// past it. None for chunks that aren't drawn at all.
+    pub fn detailLevel(&self, pos: ChunkPos) -> Option<u8> {
+        let d = pos.distance(self.center?);
+        let r = self.renderDistance;
+        if d <= r {
+            Some(0)
+        } else if d <= r + self.lodDistance {
+            let ring = (d - r - 1) * LODLEVELS as i32 / self.lodDistance;
+            Some(1 + ring as u8)
+        } else {
+            None
+        }
+    }
+
+    // call once per frame with the camera position. loads/unloads chunks when the camera
+    // crosses a chunk border, then moves a slice of the chunks on through generation and
+    // meshes the ones that are ready
+    pub fn update(&mut self, cameraPos: Point3<f32>) {
+        self.camera = cameraPos;
+        let (_, camChunk) = World::worldToLoc(cameraPos);
+        if self.center != Some(camChunk) {
+            self.recenter(camChunk);
+        }
+
+        let jobs = self.scheduleStages(GENPERFRAME);
+        if !jobs.is_empty() {
+            self.runStages(&jobs);
+        }
+
+        self.remesh_dirty(REMESHBUDGET);
+    }
+
+    // how far a chunk at `pos` should get: meshed inside the render distance, and
+    // two rings around that far enough along for the meshed ones to be final. LOD
+    // rings only need their own blocks
+    fn targetStatus(&self, pos: ChunkPos) -> Option<ChunkStatus> {
+        let d = pos.distance(self.center?);
+        let r = self.renderDistance;
+        if d <= r {
+            Some(ChunkStatus::Meshed)
+        } else if d == r + 1 {
+            Some(ChunkStatus::Lit)
+        } else if d <= r + self.lodDistance.max(2) {
+            Some(ChunkStatus::Decorated)
+        } else {
+            None
+        }
+    }
+
+    // true if every chunk around `pos` is loaded and at least at `status`
+    fn neighboursAt(&self, pos: ChunkPos, status: ChunkStatus) -> bool {
+        let around = pos.surrounding();
+        around
+            .iter()
+            .all(|n| self.chunks.get(n).is_some_and(|c| c.status >= status))
+    }
+
+    // the furthest status `pos` could go to right now without waiting on anyone,
+    // None if it's stuck. meshing is left to remesh_dirty
+    fn reachable(&self, pos: ChunkPos) -> Option<ChunkStatus> {
+        let target = self.targetStatus(pos)?.min(ChunkStatus::Lit);
+        let mut status = self.chunks[&pos].status;
+        while let Some(next) = status.next() {
+            if next > target {
+                break;
             }
+            if let Some(prereq) = next.neighbourPrereq()
+                && !self.neighboursAt(pos, prereq)
+            {
+                break;
+            }
+            status = next;
         }
+        (status > self.chunks[&pos].status).then_some(status)
     }
 
-    pub fn setAll(&mut self) {
-        let (tx, rx) = channel::unbounded::<ChunkPos>();
+    // up to `budget` chunks that can move on, nearest to the camera first
+    fn scheduleStages(&self, budget: usize) -> Vec<(ChunkPos, ChunkStatus)> {
+        let center = self.center.unwrap_or(ChunkPos::new(0, 0));
+        let mut jobs: Vec<(ChunkPos, ChunkStatus)> = self
+            .chunks
+            .keys()
+            .filter_map(|pos| self.reachable(*pos).map(|goal| (*pos, goal)))
+            .collect();
+        jobs.sort_by_key(|(pos, _)| pos.distance(center));
+        jobs.truncate(budget);
+        jobs
+    }
+
+    // meshing needs final blocks in the chunk and in the eight around it, the four it
+    // shares faces with and the diagonals its corner AO reads. LOD meshes make do with
+    // the chunk's own blocks
+    fn canMesh(&self, pos: ChunkPos) -> bool {
+        match self.detailLevel(pos) {
+            Some(0) => {
+                self.chunks[&pos].status >= ChunkStatus::Lit
+                    && self.neighboursAt(pos, ChunkStatus::Lit)
+            }
+            Some(_) => self.chunks[&pos].status >= ChunkStatus::Decorated,
+            None => false,
+        }
+    }
 
-        for key in self.chunks.keys().copied() {
-            tx.send(key);
+    // remeshes and re-uploads dirty chunks, nearest to the camera first, stopping once
+    // `budget` section meshes have been uploaded. at least one chunk is always done so a
+    // chunk with more dirty sections than the budget can't stall the queue.
+    // returns the number of sections uploaded
+    pub fn remesh_dirty(&mut self, budget: usize) -> usize {
+        if self.dirty.is_empty() {
+            return 0;
+        }
+        let center = self.center.unwrap_or(ChunkPos::new(0, 0));
+        // chunks still waiting on neighbours stay queued
+        let mut candidates: Vec<ChunkPos> = self
+            .dirty
+            .iter()
+            .copied()
+            .filter(|pos| self.canMesh(*pos))
+            .collect();
+        candidates.sort_by_key(|pos| pos.distance(center));
+
+        let mut jobs = Vec::new();
+        let mut sections = 0;
+        for pos in candidates {
+            let count = self.chunks[&pos].dirtySectionCount();
+            if !jobs.is_empty() && sections + count > budget {
+                break;
+            }
+            sections += count;
+            jobs.push(pos);
+        }
+
+        for pos in &jobs {
+            self.dirty.remove(pos);
+        }
+        self.remeshChunks(&jobs);
+        sections
+    }
+
+    fn recenter(&mut self, center: ChunkPos) {
+        self.center = Some(center);
+        let r = self.renderDistance + self.lodDistance.max(2);
+
+        // drop everything out of range, the renderer frees their meshes on its next sync
+        let gone: Vec<ChunkPos> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|pos| pos.distance(center) > r)
+            .collect();
+        for pos in gone {
+            // an edit that can't be written stays loaded (and undrawn), saveModified
+            // tries it again and reports the error
+            let chunk = &self.chunks[&pos];
+            if chunk.modified && self.store.save(chunk, &self.registry).is_err() {
+                continue;
+            }
+            self.chunks.remove(&pos);
+            self.meshUpdates.push(MeshUpdate::Unloaded(pos));
+            self.dirty.remove(&pos);
         }
-        drop(tx);
 
-        let chunks = Arc::new(Mutex::new(&mut self.chunks));
+        // new chunks start empty and get picked up by scheduleStages
+        for x in -r..=r {
+            for z in -r..=r {
+                let pos = ChunkPos::new(center.x + x, center.z + z);
+                self.chunks
+                    .entry(pos)
+                    .or_insert_with(|| Chunk::new(pos, self.height));
+            }
+        }
+
+        // chunks changing level need new meshes, and so do their neighbours' edges
+        let moved: Vec<ChunkPos> = self
+            .chunks
+            .iter()
+            .filter(|(pos, chunk)| {
+                chunk.meshLevel.is_some() && chunk.meshLevel != self.detailLevel(**pos)
+            })
+            .map(|(pos, _)| *pos)
+            .collect();
+        for pos in moved {
+            for p in std::iter::once(pos).chain(pos.neighbours()) {
+                if let Some(chunk) = self.chunks.get_mut(&p)
+                    && chunk.meshLevel.is_some()
+                {
+                    chunk.markAllDirty();
+                    self.dirty.insert(p);
+                }
+            }
+        }
+    }
+
+    // writes every edited chunk back to its region file, call before exiting. only
+    // Lit chunks can be edited, so everything saved has its neighbours' spilled blocks.
+    // a chunk that fails stays modified, the first error comes back once the rest are done
+    pub fn saveModified(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        for chunk in self.chunks.values_mut() {
+            if !chunk.modified {
+                continue;
+            }
+            chunk.compact();
+            match self.store.save(chunk, &self.registry) {
+                Ok(()) => chunk.modified = false,
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        result
+    }
+
+    // what the surrounding chunks' features put into `pos`, always in surrounding()
+    // order so overlapping blocks land the same way whichever chunk generated first
+    fn spilledInto(&self, pos: ChunkPos) -> Vec<(Vector3<usize>, Block)> {
+        pos.surrounding()
+            .iter()
+            .filter_map(|n| self.chunks.get(n)?.spill.get(&pos))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    // moves each chunk on to its goal status on the worker threads, then hands out the
+    // blocks their features spilled and queues them for meshing
+    fn runStages(&mut self, jobs: &[(ChunkPos, ChunkStatus)]) {
+        let (job_tx, job_rx) =
+            channel::unbounded::<(Chunk, ChunkStatus, Vec<(Vector3<usize>, Block)>)>();
+        let (res_tx, res_rx) = channel::unbounded::<(Chunk, ChunkStatus)>();
+
+        // gathered before any chunk leaves the map, the surrounding ones can be jobs too
+        let incoming: Vec<_> = jobs
+            .iter()
+            .map(|(pos, goal)| {
+                let lit = *goal >= ChunkStatus::Lit && self.chunks[pos].status < ChunkStatus::Lit;
+                if lit {
+                    self.spilledInto(*pos)
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        for ((pos, goal), writes) in jobs.iter().zip(incoming) {
+            let chunk = self
+                .chunks
+                .remove(pos)
+                .expect("runStages(): couldnt find chunk");
+            job_tx.send((chunk, *goal, writes)).unwrap();
+        }
+        drop(job_tx);
 
+        let registry = &self.registry;
+        let store = &self.store;
+        let generator = &*self.generator;
         thread::scope(|s| {
             for _ in 0..THREADS {
-                let rx = rx.clone();
-                let chunks_clone = Arc::clone(&chunks);
+                let job_rx = job_rx.clone();
+                let res_tx = res_tx.clone();
                 s.spawn(move |_| {
-                    while let Ok(job) = rx.recv() {
-                        let mut chunks = chunks_clone.lock().unwrap();
-                        let chunk = chunks.get_mut(&job).expect("setAll(): failed to get chunk");
-                        World::fillChunk(chunk);
+                    while let Ok((mut chunk, goal, writes)) = job_rx.recv() {
+                        let before = chunk.status;
+                        advanceChunk(&mut chunk, goal, &writes, store, registry, generator);
+                        res_tx.send((chunk, before)).unwrap();
                     }
                 });
             }
         })
         .unwrap();
+        drop(res_tx);
+
+        let mut decorated = Vec::new();
+        while let Ok((chunk, before)) = res_rx.recv() {
+            let pos = chunk.pos;
+            if before < ChunkStatus::Decorated && chunk.status >= ChunkStatus::Decorated {
+                decorated.push(pos);
+            }
+            // LOD meshes can be made from here, full ones wait in the queue for Lit
+            if chunk.status >= ChunkStatus::Decorated {
+                self.dirty.insert(pos);
+            }
+            self.chunks.insert(pos, chunk);
+        }
+        // LOD neighbours showed everything facing these while they had no blocks
+        for pos in decorated {
+            for n in pos.neighbours() {
+                if let Some(chunk) = self.chunks.get_mut(&n)
+                    && chunk.meshLevel.is_some_and(|l| l > 0)
+                {
+                    chunk.markAllDirty();
+                    self.dirty.insert(n);
+                }
+            }
+        }
+        // a neighbour of an already Lit chunk that was unloaded and generated again
+        // spills the same blocks it did the first time, so they aren't handed out again
     }
 
+    // runs generation around the current center (the origin before the first update)
+    // until every chunk is as far along as it can get, without the per frame budget
+    pub fn setAll(&mut self) {
+        if self.center.is_none() {
+            self.recenter(ChunkPos::new(0, 0));
+        }
+        loop {
+            let jobs = self.scheduleStages(usize::MAX);
+            if jobs.is_empty() {
+                break;
+            }
+            self.runStages(&jobs);
+        }
+    }
+
+    // meshes every chunk that is ready for it
     pub fn chunkRemeshAll(&mut self) {
-        let mut jobs = Vec::<ChunkPos>::new();
-        for key in self.chunks.keys().copied() {
-            jobs.push(key);
+        let jobs: Vec<ChunkPos> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|pos| self.canMesh(*pos))
+            .collect();
+        for pos in &jobs {
+            self.chunks.get_mut(pos).unwrap().markAllDirty();
+            self.dirty.remove(pos);
         }
+        self.remeshChunks(&jobs);
+    }
 
+    fn remeshChunks(&mut self, jobs: &[ChunkPos]) {
         let (job_tx, job_rx) = channel::unbounded::<ChunkPos>();
-        let (res_tx, res_rx) = channel::unbounded::<(ChunkPos, MeshData)>();
+        let (res_tx, res_rx) = channel::unbounded::<(ChunkPos, u8, Vec<(usize, SectionMesh)>)>();
 
-        for j in &jobs {
+        for j in jobs {
             job_tx.send(*j).unwrap();
         }
         drop(job_tx);
@@ -151,42 +662,53 @@ impl<'a> World<'a> {
                 let res_tx = res_tx.clone();
 
                 s.spawn(move |_| {
+                    // one face grid per worker, reused for every section it meshes
+                    let mut grid = FaceGrid::new();
                     while let Ok(pos) = job_rx.recv() {
                         let chunk = &world_ref
                             .chunks
                             .get(&pos)
-                            .expect("chunkRemeshAll(): couldnt find chunk"); // &Chunk
-                        let mesh = chunk.remesh(world_ref); // read-only
-                        res_tx.send((pos, mesh)).unwrap();
+                            .expect("remeshChunks(): couldnt find chunk"); // &Chunk
+                        let level = world_ref.detailLevel(pos).unwrap_or(0);
+                        let mesh = chunk.remesh(world_ref, level, &mut grid); // read-only
+                        res_tx.send((pos, level, mesh)).unwrap();
                     }
                 });
             }
         })
         .unwrap(); // all workers have joined here
 
-        // -------- Main thread: GPU upload & state updates --------
-        while let Ok((pos, mesh)) = res_rx.try_recv() {
+        // -------- Main thread: state updates, meshes queued for the renderer --------
+        while let Ok((pos, level, mesh)) = res_rx.try_recv() {
             let chunk = &mut self
                 .chunks
                 .get_mut(&pos)
-                .expect("chunkRemeshAll(): couldnt find chunk"); // &mut borrow *after* scope
-            chunk.uploadMesh(mesh);
+                .expect("remeshChunks(): couldnt find chunk"); // &mut borrow *after* scope
+            for (sy, _) in &mesh {
+                chunk.sections[*sy].dirty = false;
+            }
+            self.meshUpdates.push(MeshUpdate::Meshed(pos, mesh));
+            chunk.meshLevel = Some(level);
+            // a LOD mesh doesn't need the neighbours, so the chunk isn't done generating
+            if level == 0 {
+                chunk.status = ChunkStatus::Meshed;
+            }
         }
     }
 
-    pub fn renderAll(&self, proj: &Matrix4<f32>, view: &Matrix4<f32>) {
-        for chunk in self.chunks.values() {
-            chunk.draw(proj, view);
-        }
+    pub fn worldToLoc(pos: Point3<f32>) -> (Point3<i32>, ChunkPos) {
+        // 1) go from world-space floats to integer block coords with floor semantics
+        World::blockToLoc(IVec3::new(
+            pos.x.floor() as i32,
+            pos.y.floor() as i32,
+            pos.z.floor() as i32,
+        ))
     }
 
-    pub fn worldToLoc(pos: Point3<f32>) -> (Point3<i32>, ChunkPos) {
+    // same as worldToLoc for coordinates that are already whole blocks
+    pub fn blockToLoc(pos: IVec3) -> (Point3<i32>, ChunkPos) {
         let s = CHUNKSIZE as i32;
-
-        // 1) go from world-space floats to integer block coords with floor semantics
-        let wx = pos.x.floor() as i32;
-        let wy = pos.y.floor() as i32;
-        let wz = pos.z.floor() as i32;
+        let (wx, wy, wz) = (pos.x, pos.y, pos.z);
 
         // 2) Euclidean chunk coords (work for negatives too)
         let cx = wx.div_euclid(s);
@@ -200,242 +722,786 @@ impl<'a> World<'a> {
     }
 
     pub fn getBlockType(&self, pos: ChunkPos, blockPos: Point3<usize>) -> BlockId {
+        if blockPos.y >= self.height {
+            return BlockId::AIR;
+        }
         match self.chunks.get(&pos) {
-            Some(chunk) => chunk.blocks[blockPos.x][blockPos.y][blockPos.z],
-            None => BlockId::Air,
+            Some(chunk) => chunk.get(Vector3::new(blockPos.x, blockPos.y, blockPos.z)),
+            None => BlockId::AIR,
+        }
+    }
+
+    // block at world coordinates, Air for unloaded chunks and outside the column
+    pub fn get_block(&self, pos: IVec3) -> BlockId {
+        let (loc, chunkPos) = World::blockToLoc(pos);
+        if loc.y < 0 {
+            return BlockId::AIR;
+        }
+        self.getBlockType(
+            chunkPos,
+            Point3::new(loc.x as usize, loc.y as usize, loc.z as usize),
+        )
+    }
+
+    // biome of the world column holding x/z, None if its chunk isn't loaded
+    pub fn get_biome(&self, x: i32, z: i32) -> Option<BiomeId> {
+        let (loc, chunkPos) = World::blockToLoc(IVec3::new(x, 0, z));
+        self.chunks
+            .get(&chunkPos)
+            .map(|chunk| chunk.biome(loc.x as usize, loc.z as usize))
+    }
+
+    // state of the block at world coordinates, the default state where get_block is Air
+    pub fn get_block_state(&self, pos: IVec3) -> BlockState {
+        let (loc, chunkPos) = World::blockToLoc(pos);
+        if loc.y < 0 || loc.y as usize >= self.height {
+            return BlockState::default();
+        }
+        match self.chunks.get(&chunkPos) {
+            Some(chunk) => {
+                chunk
+                    .getBlock(Vector3::new(loc.x as usize, loc.y as usize, loc.z as usize))
+                    .state
+            }
+            None => BlockState::default(),
+        }
+    }
+
+    // sets a block with its default state, see set_block_state
+    pub fn set_block(&mut self, pos: IVec3, block: BlockId) -> bool {
+        self.set_block_state(pos, block, BlockState::default())
+    }
+
+    // sets a block at world coordinates and queues every chunk whose mesh can see it.
+    // returns false when the chunk isn't loaded, y is outside the column, or the chunk
+    // hasn't reached Lit (its blocks aren't final yet, and it couldn't be saved)
+    pub fn set_block_state(&mut self, pos: IVec3, block: BlockId, state: BlockState) -> bool {
+        let (loc, chunkPos) = World::blockToLoc(pos);
+        if loc.y < 0 || loc.y as usize >= self.height {
+            return false;
+        }
+        let cord = Vector3::new(loc.x as usize, loc.y as usize, loc.z as usize);
+        let Some(chunk) = self.chunks.get_mut(&chunkPos) else {
+            return false;
+        };
+        if chunk.status < ChunkStatus::Lit {
+            return false;
+        }
+        chunk.setBlock(cord, Block::new(block, state));
+        chunk.modified = true;
+        self.dirty.insert(chunkPos);
+
+        // blocks on a border are also visible from the chunks next door, and a corner
+        // block shades the diagonal one's corner AO too
+        let side = |c: usize| match c {
+            0 => -1,
+            c if c == CHUNKSIZE - 1 => 1,
+            _ => 0,
+        };
+        let (dx, dz) = (side(cord.x), side(cord.z));
+        let mut touching = Vec::new();
+        if dx != 0 {
+            touching.push((dx, 0));
+        }
+        if dz != 0 {
+            touching.push((0, dz));
+        }
+        if dx != 0 && dz != 0 {
+            touching.push((dx, dz));
+        }
+        for (ox, oz) in touching {
+            let n = ChunkPos::new(chunkPos.x + ox, chunkPos.z + oz);
+            if let Some(chunk) = self.chunks.get_mut(&n) {
+                chunk.markDirtyAround(cord.y);
+                self.dirty.insert(n);
+            }
+        }
+        self.scheduleFluidsAround(pos);
+        true
+    }
+
+    // queues a fluid update for `pos` and the 6 blocks around it, where they hold fluid
+    fn scheduleFluidsAround(&mut self, pos: IVec3) {
+        for d in std::iter::once([0, 0, 0]).chain(DIRS) {
+            let p = pos + Vector3::new(d[0] as i32, d[1] as i32, d[2] as i32);
+            if let Some(fluid) = &self.registry.get(self.get_block(p)).fluid {
+                self.fluidTicks.schedule(p, fluid.tickDelay);
+            }
+        }
+    }
+
+    // true if the chunks a fluid at `pos` reads and flows into all have their final
+    // blocks, so it doesn't mistake a chunk still generating for open air
+    fn fluidCanFlow(&self, pos: IVec3) -> bool {
+        let (_, chunkPos) = World::blockToLoc(pos);
+        std::iter::once(chunkPos)
+            .chain(chunkPos.neighbours())
+            .all(|p| {
+                self.chunks
+                    .get(&p)
+                    .is_some_and(|c| c.status >= ChunkStatus::Lit)
+            })
+    }
+
+    // runs the fluid updates due this tick, call every TICKLENGTH seconds
+    pub fn tick(&mut self) {
+        for pos in self.fluidTicks.advance() {
+            if !self.fluidCanFlow(pos) {
+                // tried again later, the chunks may be done or the fluid unloaded by then
+                if let Some(fluid) = &self.registry.get(self.get_block(pos)).fluid {
+                    self.fluidTicks.schedule(pos, fluid.tickDelay);
+                }
+                continue;
+            }
+            for (p, block) in fluid::flow(self, pos) {
+                self.set_block_state(p, block.id, block.state);
+            }
+        }
+    }
+
+    /// Full detail meshes of blocks `min` to `max` (inclusive), one per loaded
+    /// section overlapping them, each with the world block its vertices start
+    /// from. Everything outside the box meshes as air, so the cut sides are
+    /// closed. Chunks short of Lit are left out.
+    pub fn meshRegion(&self, min: IVec3, max: IVec3) -> Vec<(IVec3, SectionMesh)> {
+        let s = CHUNKSIZE as i32;
+        let top = (self.height / SECTIONSIZE) as i32 - 1;
+        let (sy0, sy1) = (
+            min.y.div_euclid(SECTIONSIZE as i32).max(0),
+            max.y.div_euclid(SECTIONSIZE as i32).min(top),
+        );
+        let borders = Default::default();
+        let mut grid = FaceGrid::new();
+        let mut meshes = Vec::new();
+        for cx in min.x.div_euclid(s)..=max.x.div_euclid(s) {
+            for cz in min.z.div_euclid(s)..=max.z.div_euclid(s) {
+                let Some(chunk) = self.chunks.get(&ChunkPos::new(cx, cz)) else {
+                    continue;
+                };
+                if chunk.status < ChunkStatus::Lit {
+                    continue;
+                }
+                for sy in sy0..=sy1 {
+                    let origin = IVec3::new(cx * s, sy * SECTIONSIZE as i32, cz * s);
+                    let mesh = chunk.remeshSection(
+                        sy as usize,
+                        self,
+                        self.mesher,
+                        &borders,
+                        Some((min, max)),
+                        &mut grid,
+                    );
+                    meshes.push((origin, mesh));
+                }
+            }
         }
+        meshes
+    }
+
+    pub fn memoryReport(&self) -> Vec<(ChunkPos, StorageReport)> {
+        self.chunks
+            .iter()
+            .map(|(pos, chunk)| (*pos, chunk.memoryReport()))
+            .collect()
     }
 }
 
+// worker side of runStages: takes one chunk through every stage up to `goal`, applying
+// `incoming` (what the surrounding chunks spilled into it) on the way to Lit. a saved
+// chunk already has its features, so loading one skips straight to Decorated
+fn advanceChunk(
+    chunk: &mut Chunk,
+    goal: ChunkStatus,
+    incoming: &[(Vector3<usize>, Block)],
+    store: &RegionStore,
+    registry: &BlockRegistry,
+    generator: &dyn WorldGenerator,
+) {
+    while chunk.status < goal {
+        let pos = chunk.pos;
+        match chunk.status {
+            ChunkStatus::Empty => {
+                chunk.modified = false;
+                match store.load(chunk, registry) {
+                    Ok(true) => {
+                        chunk.saved = true;
+                        // fresh neighbours still need what its features put into them
+                        let mut scratch = Chunk::new(pos, chunk.height());
+                        generator.terrain(pos, &mut scratch);
+                        generator.carve(pos, &mut scratch);
+                        chunk.spill = generator.decorate(pos, &mut scratch);
+                        chunk.status = ChunkStatus::Decorated;
+                        continue;
+                    }
+                    Ok(false) => generator.terrain(pos, chunk),
+                    Err(e) => {
+                        println!("failed to load chunk {:?}: {}", pos, e);
+                        chunk.clear();
+                        generator.terrain(pos, chunk);
+                    }
+                }
+            }
+            ChunkStatus::Terrain => generator.carve(pos, chunk),
+            ChunkStatus::Carved => chunk.spill = generator.decorate(pos, chunk),
+            ChunkStatus::Decorated => {
+                if !chunk.saved {
+                    chunk.applyWrites(incoming);
+                }
+            }
+            ChunkStatus::Lit | ChunkStatus::Meshed => unreachable!("meshing isn't a worker stage"),
+        }
+        chunk.status = chunk.status.next().unwrap();
+    }
+    // carving and filling air leave block types behind that no block uses any more
+    chunk.compact();
+    chunk.markAllDirty();
+}
+
+// chunk vertices are two u32s, unpacked again in shaderAtlas.vs:
+//   word 0: x 9 bits | y 9 | z 9 | face 3 | ao 2
+//   word 1: u 9 bits | v 9 | texture layer 14
+// x, y and z are inside the section and u and v along the texture, all in
+// SUBDIV steps per block so models can have thin parts. face is a DIRS index,
+// or 6 and 7 for the two planes of a cross model, and ao is how much light
+// reaches the corner, 0 (boxed in) to 3 (open)
+pub const VERTEXWORDS: usize = 2;
+
+// inclusive world block box meshing is limited to, everything outside is air
+type Clip = Option<(IVec3, IVec3)>;
+pub const SUBDIV: u32 = 16;
+
+pub fn packVertex(pos: Vector3<u32>, face: u32, uv: [u32; 2], layer: u32, ao: u8) -> [u32; 2] {
+    [
+        pos.x | pos.y << 9 | pos.z << 18 | face << 27 | (ao as u32) << 30,
+        uv[0] | uv[1] << 9 | layer << 18,
+    ]
+}
+
+// position of a packed vertex in SUBDIV steps
+pub fn unpackPos(word: u32) -> Vector3<u32> {
+    Vector3::new(word & 511, (word >> 9) & 511, (word >> 18) & 511)
+}
+
+#[derive(Default)]
 pub struct MeshData {
-    pub vertices: Vec<f32>,
+    pub vertices: Vec<u32>,
     pub indices: Vec<u32>,
 }
 
-#[repr(u8)]
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum BlockId {
-    Air = 3,
-    Dirt = 0,
-    Grass = 1,
+impl MeshData {
+    // `face` in direction `d` covering `size` blocks from the block at `at` (section
+    // local), with the texture repeated once per block
+    pub fn pushQuad(&mut self, d: usize, at: Vector3<u32>, size: Vector3<u32>, face: &Face) {
+        let (ua, va) = FACEAXES[d];
+        let turns = face.turns as usize;
+        // a quarter turn swaps which edge of the quad the texture's u runs along
+        let (mut eu, mut ev) = (size[ua], size[va]);
+        if turns % 2 == 1 {
+            (eu, ev) = (ev, eu);
+        }
+        // fluids that aren't full lower their top corners, and side faces crop
+        // the texture by the same amount instead of squashing it
+        let height = (face.height * SUBDIV as f32).round() as u32;
+        let mut corners = [Vector3::new(0, 0, 0); 4];
+        let mut uvs = [[0; 2]; 4];
+        // the state may rotate the texture, so corners take the uv of the
+        // corner `turns` steps further round the face
+        for c in 0..4 {
+            let corner = (120 / 6 * d) + c * 5;
+            let uv = (120 / 6 * d) + ((c + turns) % 4) * 5 + 3;
+            // 0 or 1 along each axis of the quad
+            let side = |i: usize| (vertices[corner + i] + 0.5) as u32;
+            corners[c] =
+                (at + Vector3::new(side(0), side(1), side(2)).mul_element_wise(size)) * SUBDIV;
+            uvs[c] = [
+                vertices[uv] as u32 * eu * SUBDIV,
+                vertices[uv + 1] as u32 * ev * SUBDIV,
+            ];
+            if side(1) == 1 && height < SUBDIV {
+                corners[c].y -= SUBDIV - height;
+                if d < 4 {
+                    uvs[c][1] = height;
+                }
+            }
+        }
+        self.pushRaw(d as u32, corners, uvs, face.layer, face.ao);
+    }
+
+    // one quad from its corners in DIRS corner order, positions and uvs in SUBDIV steps
+    pub fn pushRaw(
+        &mut self,
+        face: u32,
+        corners: [Vector3<u32>; 4],
+        uvs: [[u32; 2]; 4],
+        layer: u32,
+        ao: [u8; 4],
+    ) {
+        let next = self.vertexCount() as u32;
+        for c in 0..4 {
+            self.vertices
+                .extend(packVertex(corners[c], face, uvs[c], layer, ao[c]));
+        }
+        // split along the darker diagonal, so a single shaded corner fades out
+        // evenly instead of leaving a streak along the other diagonal
+        let quad = if ao[0] + ao[2] > ao[1] + ao[3] {
+            [next + 1, next + 2, next + 3, next + 1, next + 3, next]
+        } else {
+            [next, next + 1, next + 2, next, next + 2, next + 3]
+        };
+        self.indices.extend_from_slice(&quad);
+    }
+
+    pub fn vertexCount(&self) -> usize {
+        self.vertices.len() / VERTEXWORDS
+    }
+
+    // centre and indices of each quad, for sorting them
+    pub fn quads(&self) -> Vec<(Vector3<f32>, [u32; 6])> {
+        self.indices
+            .chunks_exact(6)
+            .map(|quad| {
+                let first = quad.iter().min().unwrap();
+                let centre = (0..4)
+                    .map(|c| unpackPos(self.vertices[(first + c) as usize * VERTEXWORDS]))
+                    .fold(Vector3::new(0.0, 0.0, 0.0), |sum, v| {
+                        sum + v.cast::<f32>().unwrap() / (4 * SUBDIV) as f32
+                    });
+                (centre, quad.try_into().unwrap())
+            })
+            .collect()
+    }
 }
 
-pub struct Chunk<'a> {
-    blocks: Box<[[[BlockId; CHUNKSIZE]; CHUNKHIEGHT]; CHUNKSIZE]>,
-    shader: &'a Shader,
-    VAO: u32,
-    VBO: u32,
-    EBO: u32,
-    texture: u32,
-    // verts: Vec<f32>,
-    // vertexCount: i32,
-    indexCount: i32,
-    pos: ChunkPos,
+// what one section meshes into, one mesh per RenderClass uploaded into separate buffers
+#[derive(Default)]
+pub struct SectionMesh {
+    pub opaque: MeshData,
+    pub cutout: MeshData,
+    pub translucent: MeshData,
+}
+
+impl SectionMesh {
+    pub fn class(&mut self, class: RenderClass) -> &mut MeshData {
+        match class {
+            RenderClass::Opaque => &mut self.opaque,
+            RenderClass::Cutout => &mut self.cutout,
+            RenderClass::Translucent => &mut self.translucent,
+        }
+    }
+}
+
+// a 16x16x16 slice of a chunk column, stored and meshed on its own
+struct Section {
+    // indexed by Section::blockIndex(), palette compressed since most sections only hold a few block types
+    blocks: PalettedContainer<Block>,
+    dirty: bool,
 }
 
-impl<'a> Chunk<'a> {
-    pub fn new(shader: &'a Shader, pos: ChunkPos, texture: u32) -> Self {
-        let (mut VBO, mut VAO, mut EBO) = (0, 0, 0);
-
-        unsafe {
-            gl::GenVertexArrays(1, &mut VAO);
-            gl::GenBuffers(1, &mut VBO);
-            gl::GenBuffers(1, &mut EBO);
-
-            gl::BindVertexArray(VAO);
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, VBO);
-
-            let stride = 6 * mem::size_of::<GLfloat>() as GLsizei;
-            // position attribute
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
-            gl::EnableVertexAttribArray(0);
-            // texture coord attribute
-            gl::VertexAttribPointer(
-                1,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                stride,
-                (3 * mem::size_of::<GLfloat>()) as *const c_void,
-            );
-            gl::EnableVertexAttribArray(1);
-
-            gl::VertexAttribPointer(
-                2,
-                1,
-                gl::FLOAT,
-                gl::FALSE,
-                stride,
-                (5 * mem::size_of::<GLfloat>()) as *const c_void,
-            );
-            gl::EnableVertexAttribArray(2);
+impl Section {
+    fn new() -> Self {
+        Self {
+            blocks: PalettedContainer::new(SECTIONSIZE * SECTIONSIZE * SECTIONSIZE, Block::AIR),
+            dirty: false,
         }
+    }
 
+    fn blockIndex(x: usize, y: usize, z: usize) -> usize {
+        (x * SECTIONSIZE + y) * SECTIONSIZE + z
+    }
+
+    fn isEmpty(&self) -> bool {
+        self.blocks.isUniform() && self.blocks.get(0).id == BlockId::AIR
+    }
+}
+
+pub struct Chunk {
+    // bottom to top, height / SECTIONSIZE of them
+    sections: Vec<Section>,
+    pos: ChunkPos,
+    // edited since it was loaded or generated, needs writing to its region file
+    modified: bool,
+    // came from its region file, which already has the neighbours' spilled blocks
+    saved: bool,
+    // one per column, indexed x * CHUNKSIZE + z
+    biomes: Vec<BiomeId>,
+    status: ChunkStatus,
+    // World::detailLevel the uploaded meshes were made at, None before the first
+    meshLevel: Option<u8>,
+    // blocks this chunk's features put into its neighbours, by neighbour. kept while
+    // it's loaded so each one can take its share when it goes Lit
+    spill: PendingWrites,
+}
+
+impl Chunk {
+    pub fn new(pos: ChunkPos, height: usize) -> Self {
+        assert!(
+            height.is_multiple_of(SECTIONSIZE),
+            "Chunk::new(): height must be a multiple of {}",
+            SECTIONSIZE
+        );
         Self {
-            blocks: Box::new([[[BlockId::Air; CHUNKSIZE]; CHUNKHIEGHT]; CHUNKSIZE]),
-            shader,
-            VAO,
-            VBO,
-            EBO,
-            texture,
-            // verts: Vec::with_capacity(CHUNKSIZE * CHUNKSIZE * CHUNKHIEGHT * 4 * 6),
-            // vertexCount: 0,
-            indexCount: 0,
+            sections: (0..height / SECTIONSIZE).map(|_| Section::new()).collect(),
             pos,
+            modified: false,
+            saved: false,
+            biomes: vec![BiomeId::default(); CHUNKSIZE * CHUNKSIZE],
+            status: ChunkStatus::Empty,
+            meshLevel: None,
+            spill: PendingWrites::new(),
         }
     }
 
-    pub fn set(&mut self, cord: Vector3<usize>, block: BlockId) {
-        self.blocks[cord.x][cord.y][cord.z] = block;
+    pub fn status(&self) -> ChunkStatus {
+        self.status
     }
 
-    pub fn remesh(&self, world: &World) -> MeshData {
-        let mut verts: Vec<f32> = Vec::with_capacity(CHUNKSIZE * CHUNKSIZE * CHUNKHIEGHT * 6);
-        let mut inds: Vec<u32> = Vec::new();
-        let mut next = 0u32;
+    pub fn biome(&self, x: usize, z: usize) -> BiomeId {
+        self.biomes[x * CHUNKSIZE + z]
+    }
 
-        for x in 0..CHUNKSIZE {
-            for y in 0..CHUNKHIEGHT {
-                for z in 0..CHUNKSIZE {
-                    let id = self.blocks[x][y][z];
-                    if id == BlockId::Air {
-                        continue;
-                    }
+    pub fn setBiome(&mut self, x: usize, z: usize, biome: BiomeId) {
+        self.biomes[x * CHUNKSIZE + z] = biome;
+    }
 
-                    for d in 0..6 {
-                        let mut isEnd: bool = false;
-                        let mut dx = DIRS[d][0] + x as i16;
-                        let dy = DIRS[d][1] + y as i16;
-                        let mut dz = DIRS[d][2] + z as i16;
+    pub fn pos(&self) -> ChunkPos {
+        self.pos
+    }
 
-                        if dy < 0 || dy >= (CHUNKHIEGHT as i16) {
-                            isEnd = true;
-                        }
+    // back to all air, generation starts over
+    pub fn clear(&mut self) {
+        for section in &mut self.sections {
+            section.blocks.fill(Block::AIR);
+            section.dirty = true;
+        }
+        self.status = ChunkStatus::Empty;
+    }
 
-                        if !isEnd {
-                            // Work out which chunk (cx, cz) we should peek into
-                            let mut cx = self.pos.x; // current chunk-coords (i32)
-                            let mut cz = self.pos.z;
-
-                            if dx < 0 {
-                                cx -= 1; // step West
-                                dx += CHUNKSIZE as i16; // wrap into [0, CHUNKSIZE-1]
-                            } else if dx >= CHUNKSIZE as i16 {
-                                cx += 1; // step East
-                                dx -= CHUNKSIZE as i16;
-                            }
+    pub fn height(&self) -> usize {
+        self.sections.len() * SECTIONSIZE
+    }
 
-                            if dz < 0 {
-                                cz -= 1; // step North
-                                dz += CHUNKSIZE as i16;
-                            } else if dz >= CHUNKSIZE as i16 {
-                                cz += 1; // step South
-                                dz -= CHUNKSIZE as i16;
-                            }
+    pub fn get(&self, cord: Vector3<usize>) -> BlockId {
+        self.getBlock(cord).id
+    }
 
-                            // Look up the block in whatever chunk we ended up in
-                            if world.getBlockType(
-                                ChunkPos { x: cx, z: cz },
-                                Point3 {
-                                    x: dx as usize,
-                                    y: dy as usize,
-                                    z: dz as usize,
-                                },
-                            ) == BlockId::Air
-                            {
-                                isEnd = true; // neighbour is air → expose this face
-                            }
-                        }
+    pub fn getBlock(&self, cord: Vector3<usize>) -> Block {
+        let section = &self.sections[cord.y / SECTIONSIZE];
+        section
+            .blocks
+            .get(Section::blockIndex(cord.x, cord.y % SECTIONSIZE, cord.z))
+    }
 
-                        if isEnd {
-                            let mut face: Vec<f32> = vec![];
-                            face.extend(&vertices[(120 / 6 * d)..(120 / 6 * d) + 5]);
-                            face.push(id as i32 as f32);
-                            face.extend(&vertices[(120 / 6 * d) + 5..(120 / 6 * d) + 10]);
-                            face.push(id as i32 as f32);
-                            face.extend(&vertices[(120 / 6 * d) + 10..(120 / 6 * d) + 15]);
-                            face.push(id as i32 as f32);
-                            face.extend(&vertices[(120 / 6 * d) + 15..(120 / 6 * d) + 20]);
-                            face.push(id as i32 as f32);
-
-                            for indx in 0..face.len() {
-                                if indx % 6 == 0 {
-                                    face[indx] += 1. * x as f32;
-                                }
-                                if indx % 6 == 1 {
-                                    face[indx] += (1. * y as f32) - CHUNKHIEGHT as f32;
-                                }
-                                if indx % 6 == 2 {
-                                    face[indx] += 1. * z as f32;
-                                }
-                            }
-                            verts.extend(face.iter().clone());
-                            let indsSlice = &[next, next + 1, next + 2, next, next + 2, next + 3];
-                            inds.extend_from_slice(indsSlice);
-                            next += 4;
-                        }
-                    }
-                }
+    // sets the block with its default state
+    pub fn set(&mut self, cord: Vector3<usize>, block: BlockId) {
+        self.setBlock(cord, Block::from(block));
+    }
+
+    pub fn setBlock(&mut self, cord: Vector3<usize>, block: Block) {
+        let sy = cord.y / SECTIONSIZE;
+        let ly = cord.y % SECTIONSIZE;
+        let section = &mut self.sections[sy];
+        section
+            .blocks
+            .set(Section::blockIndex(cord.x, ly, cord.z), block);
+        section.dirty = true;
+
+        // the face between two sections belongs to both meshes, and the AO next to it
+        if ly == 0 && sy > 0 {
+            self.sections[sy - 1].dirty = true;
+        }
+        if ly == SECTIONSIZE - 1 && sy + 1 < self.sections.len() {
+            self.sections[sy + 1].dirty = true;
+        }
+    }
+
+    // blocks queued by a neighbour's features, only filling air
+    pub fn applyWrites(&mut self, writes: &[(Vector3<usize>, Block)]) {
+        for (cord, block) in writes {
+            if cord.y < self.height() && self.get(*cord) == BlockId::AIR {
+                self.setBlock(*cord, *block);
             }
         }
+    }
+
+    pub fn dirtySectionCount(&self) -> usize {
+        self.sections.iter().filter(|s| s.dirty).count()
+    }
+
+    // flags the sections that can see a block at local height y for the next remesh,
+    // which reaches one block up and down since corner AO looks at the blocks around
+    pub fn markDirtyAround(&mut self, y: usize) {
+        let top = (y + 1).min(self.height() - 1);
+        for sy in y.saturating_sub(1) / SECTIONSIZE..=top / SECTIONSIZE {
+            self.sections[sy].dirty = true;
+        }
+    }
+
+    pub fn isSectionDirty(&self, sy: usize) -> bool {
+        self.sections[sy].dirty
+    }
+
+    // flags every section for the next remesh, e.g. after a neighbour chunk changed
+    pub fn markAllDirty(&mut self) {
+        for section in &mut self.sections {
+            section.dirty = true;
+        }
+    }
+
+    // drops palette entries edits and generation left unused
+    fn compact(&mut self) {
+        for section in &mut self.sections {
+            section.blocks.compact();
+        }
+    }
 
-        verts.shrink_to_fit();
-        // self.vertexCount = self.verts.len() as i32;
-        // self.indexCount = inds.len() as i32;
+    pub fn memoryReport(&self) -> StorageReport {
+        self.sections
+            .iter()
+            .map(|s| s.blocks.report())
+            .reduce(|a, b| a + b)
+            .expect("Chunk::memoryReport(): chunk has no sections")
+    }
 
-        MeshData {
-            vertices: verts,
-            indices: inds,
+    // block at chunk-local coords that may step one block outside this chunk, air
+    // outside `clip`
+    fn neighbourBlock(&self, world: &World, clip: Clip, x: i32, y: i32, z: i32) -> BlockId {
+        if y < 0 || y >= self.height() as i32 || !self.inClip(clip, x, y, z) {
+            return BlockId::AIR;
         }
+        let s = CHUNKSIZE as i32;
+        if (0..s).contains(&x) && (0..s).contains(&z) {
+            return self.get(Vector3::new(x as usize, y as usize, z as usize));
+        }
+        // Work out which chunk we should peek into, div/rem_euclid wrap into [0, CHUNKSIZE-1]
+        world.getBlockType(
+            ChunkPos {
+                x: self.pos.x + x.div_euclid(s),
+                z: self.pos.z + z.div_euclid(s),
+            },
+            Point3 {
+                x: x.rem_euclid(s) as usize,
+                y: y as usize,
+                z: z.rem_euclid(s) as usize,
+            },
+        )
     }
 
-    pub fn uploadMesh(&mut self, data: MeshData) {
-        self.indexCount = data.indices.len() as i32;
-        unsafe {
-            gl::BindVertexArray(self.VAO);
+    // whether chunk-local (x, y, z) is inside the world block box `clip`, if any
+    fn inClip(&self, clip: Clip, x: i32, y: i32, z: i32) -> bool {
+        let Some((min, max)) = clip else {
+            return true;
+        };
+        let s = CHUNKSIZE as i32;
+        let at = IVec3::new(self.pos.x * s + x, y, self.pos.z * s + z);
+        (0..3).all(|i| (min[i]..=max[i]).contains(&at[i]))
+    }
 
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.VBO);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (data.vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-                &data.vertices[0] as *const f32 as *const c_void,
-                gl::STATIC_DRAW,
-            );
+    // light reaching each corner of the face in direction `d` of block (x, y, z),
+    // from the two blocks beside the corner and the one diagonal to it in the
+    // layer the face looks into. may look into any of the 8 chunks around
+    fn faceAo(&self, world: &World, clip: Clip, x: i32, y: i32, z: i32, d: usize) -> [u8; 4] {
+        let registry = world.registry();
+        let p = Vector3::new(
+            x + DIRS[d][0] as i32,
+            y + DIRS[d][1] as i32,
+            z + DIRS[d][2] as i32,
+        );
+        let opaque = |p: IVec3| {
+            registry
+                .get(self.neighbourBlock(world, clip, p.x, p.y, p.z))
+                .opaque as u8
+        };
+        let (ua, va) = FACEAXES[d];
+        let mut ao = [3; 4];
+        for (c, light) in ao.iter_mut().enumerate() {
+            let corner = (120 / 6 * d) + c * 5;
+            let (mut du, mut dv) = (IVec3::new(0, 0, 0), IVec3::new(0, 0, 0));
+            du[ua] = if vertices[corner + ua] > 0.0 { 1 } else { -1 };
+            dv[va] = if vertices[corner + va] > 0.0 { 1 } else { -1 };
+            let (side1, side2) = (opaque(p + du), opaque(p + dv));
+            *light = if side1 == 1 && side2 == 1 {
+                0
+            } else {
+                3 - side1 - side2 - opaque(p + du + dv)
+            };
+        }
+        ao
+    }
 
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.EBO);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (data.indices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-                &data.indices[0] as *const u32 as *const c_void,
-                gl::STATIC_DRAW,
-            );
+    // meshes every dirty section at World::detailLevel `level`, returning (section
+    // index, mesh) pairs. `grid` is scratch space for the faces, shared by the sections
+    pub fn remesh(
+        &self,
+        world: &World,
+        level: u8,
+        grid: &mut FaceGrid,
+    ) -> Vec<(usize, SectionMesh)> {
+        let dirty = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.dirty)
+            .map(|(sy, _)| sy);
+        if level > 0 {
+            return self.remeshLod(world, level, dirty, grid);
         }
+
+        let borders = self.borderGrids(world, 1);
+        dirty
+            .map(|sy| {
+                (
+                    sy,
+                    self.remeshSection(sy, world, world.mesher, &borders, None, grid),
+                )
+            })
+            .collect()
     }
 
-    pub fn draw(&self, proj: &Matrix4<f32>, view: &Matrix4<f32>) {
-        unsafe {
-            self.shader.useProgram();
-            self.shader.setMat4(c"projection", proj);
-            self.shader.setMat4(c"view", view);
+    // the chunks across each side (the first four DIRS) as they're drawn, where that's
+    // at `minLevel` or coarser, for culling faces on the edge against what's actually
+    // there. None where nothing is drawn yet
+    fn borderGrids(&self, world: &World, minLevel: u8) -> [Option<LodGrid>; 4] {
+        std::array::from_fn(|d| {
+            let n = self.pos.step(d);
+            let level = world.detailLevel(n).filter(|l| *l >= minLevel)?;
+            let chunk = world.chunks.get(&n)?;
+            (chunk.status >= ChunkStatus::Decorated)
+                .then(|| LodGrid::build(chunk, world.registry(), level, world.lodPick))
+        })
+    }
 
-            let model = cgmath::Matrix4::<f32>::from_translation(Vector3 {
-                x: self.pos.x as f32 * 16.0 + 0.5,
-                y: 0.0,
-                z: self.pos.z as f32 * 16.0 + 0.5,
-            });
-            self.shader.setMat4(c"model", &model);
+    fn remeshLod(
+        &self,
+        world: &World,
+        level: u8,
+        dirty: impl Iterator<Item = usize>,
+        grid: &mut FaceGrid,
+    ) -> Vec<(usize, SectionMesh)> {
+        let registry = world.registry();
+        let lod = LodGrid::build(self, registry, level, world.lodPick);
+        let borders = self.borderGrids(world, 0);
+        dirty
+            .map(|sy| {
+                (
+                    sy,
+                    lod.meshSection(sy, &borders, registry, world.mesher, grid),
+                )
+            })
+            .collect()
+    }
+
+    // `borders` are the LOD neighbours from borderGrids(), blocks outside `clip` are
+    // left out and faces next to them drawn. `grid` is reset before use
+    fn remeshSection(
+        &self,
+        sy: usize,
+        world: &World,
+        mesher: Mesher,
+        borders: &[Option<LodGrid>; 4],
+        clip: Clip,
+        grid: &mut FaceGrid,
+    ) -> SectionMesh {
+        let section = &self.sections[sy];
+        if section.isEmpty() {
+            return SectionMesh::default();
+        }
 
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.texture);
+        let registry = world.registry();
+        let mut mesh = SectionMesh::default();
+        // visible faces are collected first, then turned into quads by the mesher
+        grid.reset(1);
 
-            gl::BindVertexArray(self.VAO);
-            gl::DrawElements(
-                gl::TRIANGLES,
-                self.indexCount,
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-            );
+        for x in 0..CHUNKSIZE {
+            for y in 0..SECTIONSIZE {
+                for z in 0..CHUNKSIZE {
+                    let block = section.blocks.get(Section::blockIndex(x, y, z));
+                    let wy = (sy * SECTIONSIZE + y) as i32;
+                    if block.id == BlockId::AIR || !self.inClip(clip, x as i32, wy, z as i32) {
+                        continue;
+                    }
+                    let def = registry.get(block.id);
+                    let neighbour = |d: usize| {
+                        self.neighbourBlock(
+                            world,
+                            clip,
+                            x as i32 + DIRS[d][0] as i32,
+                            wy + DIRS[d][1] as i32,
+                            z as i32 + DIRS[d][2] as i32,
+                        )
+                    };
+
+                    // models don't line up with anything, so they skip the mesher
+                    if let Some(model) = &def.model {
+                        let at = Vector3::new(x as u32, y as u32, z as u32);
+                        model.bake(def, block.state, mesh.class(def.render), at, |d| {
+                            registry.get(neighbour(d)).opaque
+                        });
+                        continue;
+                    }
+
+                    // fluids are full height under more of the same fluid or while
+                    // falling, otherwise lower the further they have flowed
+                    let fluid = def.fluid.is_some();
+                    let height = if !fluid || neighbour(5) == block.id || block.state.falling() {
+                        1.0
+                    } else {
+                        (8 - block.state.level()) as f32 / 9.0
+                    };
+                    // translucent blocks and fluids don't show faces inside themselves
+                    let merges = fluid || def.render == RenderClass::Translucent;
+
+                    for d in 0..6 {
+                        let n = neighbour(d);
+                        let last = CHUNKSIZE - 1;
+                        let edge = [z == 0, z == last, x == 0, x == last, false, false][d];
+                        let hidden = match &borders[d.min(3)] {
+                            // a coarser neighbour may be open where its blocks aren't, and the
+                            // other way round, so edges go by the cells it draws
+                            Some(other) if edge => {
+                                let at = Vector3::new(
+                                    (x as i32 + DIRS[d][0] as i32).rem_euclid(CHUNKSIZE as i32),
+                                    wy,
+                                    (z as i32 + DIRS[d][2] as i32).rem_euclid(CHUNKSIZE as i32),
+                                )
+                                .cast::<usize>()
+                                .unwrap();
+                                other.covers(registry, block.id, at, at)
+                            }
+                            // anything can be seen through cutout and translucent neighbours
+                            _ => registry.get(n).opaque || (merges && n == block.id),
+                        };
+                        if hidden {
+                            continue;
+                        }
+                        let (layer, turns) = def.faceTexture(block.state, d);
+                        let face = Face {
+                            class: def.render,
+                            layer,
+                            turns,
+                            height,
+                            ao: if fluid {
+                                [3; 4]
+                            } else {
+                                self.faceAo(world, clip, x as i32, wy, z as i32, d)
+                            },
+                        };
+                        grid.set(d, x, y, z, face);
+                    }
+                }
+            }
+        }
+
+        match mesher {
+            Mesher::Naive => grid.pushEach(&mut mesh),
+            Mesher::Greedy => grid.merge(&mut mesh),
+        }
+        for class in [
+            RenderClass::Opaque,
+            RenderClass::Cutout,
+            RenderClass::Translucent,
+        ] {
+            mesh.class(class).vertices.shrink_to_fit();
         }
+        mesh
     }
 }
@@ -0,0 +1,51 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use transformations::biome::{BIOMES, BiomeId, ClimateMap};
+
+#[test]
+fn columnsTakeTheNearestBiome() {
+    let map = ClimateMap::new(99);
+    let (lowest, highest) = BIOMES.iter().fold((f64::MAX, f64::MIN), |(lo, hi), b| {
+        (lo.min(b.heightOffset), hi.max(b.heightOffset))
+    });
+    let mut seen = [false; BIOMES.len()];
+    for wx in (-4096..4096).step_by(64) {
+        for wz in (-4096..4096).step_by(64) {
+            let (t, h) = map.climate(wx, wz);
+            let distance = |b: &BiomeId| {
+                let def = b.def();
+                (def.temperature - t).powi(2) + (def.humidity - h).powi(2)
+            };
+            let column = map.column(wx, wz);
+            let nearest = (0..BIOMES.len() as u8)
+                .map(BiomeId)
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                .unwrap();
+            assert_eq!(column.biome, nearest, "at {}, {}", wx, wz);
+            // blended between biomes, never past them
+            assert!((lowest..=highest).contains(&column.heightOffset));
+            seen[column.biome.0 as usize] = true;
+        }
+    }
+    assert!(
+        seen.iter().filter(|s| **s).count() > 1,
+        "only one biome in 8k blocks"
+    );
+}
+
+#[test]
+fn worldBiomesFollowTheClimate() {
+    let save = common::tempDir("biome");
+    let world = common::noiseWorld(5, &save);
+    let map = ClimateMap::new(5);
+    for x in -48..64 {
+        for z in -48..64 {
+            assert_eq!(world.get_biome(x, z), Some(map.column(x, z).biome));
+        }
+    }
+    // nothing loaded out there
+    assert_eq!(world.get_biome(1000, 0), None);
+    std::fs::remove_dir_all(save).unwrap();
+}
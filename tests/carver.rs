@@ -0,0 +1,109 @@
+#![allow(non_snake_case)]
+
+use cgmath::Vector3;
+use std::collections::HashMap;
+use transformations::block::{BlockId, BlockRegistry};
+use transformations::carver::CaveCarver;
+use transformations::world::{BLOCKDEFS, CHUNKSIZE, Chunk, ChunkPos};
+
+fn filled(pos: ChunkPos, block: BlockId) -> Chunk {
+    let mut chunk = Chunk::new(pos, 128);
+    for x in 0..CHUNKSIZE {
+        for z in 0..CHUNKSIZE {
+            for y in 0..chunk.height() {
+                chunk.set(Vector3::new(x, y, z), block);
+            }
+        }
+    }
+    chunk
+}
+
+#[test]
+fn cavesLeaveTheFloorAndWaterAlone() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let (stone, water) = (registry.id("stone").unwrap(), registry.id("water").unwrap());
+    let mut carver = CaveCarver::new(1, &registry);
+    // every cheese cell is open
+    carver.cheeseThreshold = -10.0;
+
+    let pos = ChunkPos::new(0, 0);
+    let mut chunk = filled(pos, stone);
+    for x in 0..CHUNKSIZE {
+        for z in 0..CHUNKSIZE {
+            for y in 60..chunk.height() {
+                chunk.set(Vector3::new(x, y, z), water);
+            }
+        }
+    }
+    carver.carve(pos, &mut chunk);
+
+    let at = |y| chunk.get(Vector3::new(7, y, 7));
+    assert_eq!(at(0), stone, "the bottom layer was carved");
+    assert_eq!(at(30), BlockId::AIR);
+    assert_eq!(at(59), stone, "the block holding the water up was carved");
+    assert_eq!(at(60), water);
+}
+
+#[test]
+fn wormsCrossChunkBorders() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let stone = registry.id("stone").unwrap();
+    let mut carver = CaveCarver::new(42, &registry);
+    // worms only, and from every chunk
+    carver.cheeseThreshold = 10.0;
+    carver.wormRarity = 1;
+
+    // each chunk carved on its own, like the world does
+    let mut chunks = HashMap::new();
+    for x in 0..3 {
+        for z in 0..3 {
+            let pos = ChunkPos::new(x, z);
+            let mut chunk = filled(pos, stone);
+            carver.carve(pos, &mut chunk);
+            chunks.insert((x, z), chunk);
+        }
+    }
+    // and again in the other order, which has to give the same blocks
+    for x in (0..3).rev() {
+        for z in (0..3).rev() {
+            let pos = ChunkPos::new(x, z);
+            let mut chunk = filled(pos, stone);
+            carver.carve(pos, &mut chunk);
+            for cx in 0..CHUNKSIZE {
+                for cz in 0..CHUNKSIZE {
+                    for y in 0..chunk.height() {
+                        let cord = Vector3::new(cx, y, cz);
+                        assert_eq!(chunk.get(cord), chunks[&(x, z)].get(cord));
+                    }
+                }
+            }
+        }
+    }
+
+    // along the borders between chunks west to east, air facing air means a
+    // tunnel carries on, air facing stone means it stops at the border
+    let (mut through, mut cut) = (0, 0);
+    for x in 0..2 {
+        for z in 0..3 {
+            let (west, east) = (&chunks[&(x, z)], &chunks[&(x + 1, z)]);
+            for cz in 0..CHUNKSIZE {
+                for y in 0..west.height() {
+                    let a = west.get(Vector3::new(CHUNKSIZE - 1, y, cz)) == BlockId::AIR;
+                    let b = east.get(Vector3::new(0, y, cz)) == BlockId::AIR;
+                    match (a, b) {
+                        (true, true) => through += 1,
+                        (true, false) | (false, true) => cut += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    assert!(through > 0, "no tunnel crosses a chunk border");
+    assert!(
+        through > cut,
+        "{} cells carry on, {} stop at a border",
+        through,
+        cut
+    );
+}
@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use transformations::block::BlockRegistry;
+use transformations::generator::{FlatGenerator, NoiseGenerator};
+use transformations::world::{BLOCKDEFS, World};
+
+/// Empty directory under the system temp dir, unique to this test process.
+pub fn tempDir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("transformations-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// 3x3 meshed chunks and the two rings they need, no LOD, saving into `save`
+fn small(mut world: World, save: &PathBuf) -> World {
+    world.setSaveDir(save);
+    world.setRenderDistance(1);
+    world.setLodDistance(0);
+    world.setAll();
+    world
+}
+
+pub fn flatWorld(save: &PathBuf) -> World {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let generator = FlatGenerator::new(&registry);
+    small(World::new(registry, Box::new(generator)), save)
+}
+
+pub fn noiseWorld(seed: u64, save: &PathBuf) -> World {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let generator = NoiseGenerator::new(seed, &registry);
+    small(World::new(registry, Box::new(generator)), save)
+}
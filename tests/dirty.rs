@@ -0,0 +1,34 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use transformations::world::{ChunkPos, IVec3, SECTIONSIZE, World};
+
+#[test]
+fn cornerEditDirtiesTheDiagonalChunk() {
+    let save = common::tempDir("dirty");
+    let mut world = common::flatWorld(&save);
+    world.chunkRemeshAll();
+    let clean = |world: &World, pos| world.chunk(pos).unwrap().dirtySectionCount() == 0;
+    assert!(clean(&world, ChunkPos::new(1, 1)));
+
+    // the top corner of chunk 0,0, on a section's bottom row so the one below shades too
+    let y = 6 * SECTIONSIZE as i32;
+    let stone = world.registry().id("stone").unwrap();
+    world.set_block(IVec3::new(15, y, 15), stone);
+    for pos in [
+        ChunkPos::new(1, 0),
+        ChunkPos::new(0, 1),
+        ChunkPos::new(1, 1),
+    ] {
+        let chunk = world.chunk(pos).unwrap();
+        assert!(
+            chunk.isSectionDirty(5) && chunk.isSectionDirty(6),
+            "{:?} wasn't dirtied",
+            pos
+        );
+        assert_eq!(chunk.dirtySectionCount(), 2);
+    }
+    assert!(clean(&world, ChunkPos::new(-1, -1)));
+    std::fs::remove_dir_all(save).unwrap();
+}
@@ -0,0 +1,37 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use transformations::export::ExportMesh;
+use transformations::mesher::Mesher;
+use transformations::world::IVec3;
+
+#[test]
+fn cutBoxIsClosed() {
+    let save = common::tempDir("export");
+    let mut world = common::flatWorld(&save);
+    world.setMesher(Mesher::Naive);
+    // solid dirt all round, crossing a chunk border in x
+    let (min, max) = (IVec3::new(12, 100, 3), IVec3::new(16, 110, 9));
+    let mesh = ExportMesh::collect(&world, min, max);
+
+    let quads: usize = mesh.groups.iter().map(|g| g.indices.len() / 6).sum();
+    let (w, h, d) = (5, 11, 7);
+    assert_eq!(
+        quads,
+        2 * (w * h + w * d + h * d),
+        "one quad per block face on the surface"
+    );
+    for g in &mesh.groups {
+        for p in &g.positions {
+            for i in 0..3 {
+                assert!(
+                    p[i] >= min[i] as f32 && p[i] <= (max[i] + 1) as f32,
+                    "{:?} is outside the box",
+                    p
+                );
+            }
+        }
+    }
+    std::fs::remove_dir_all(save).unwrap();
+}
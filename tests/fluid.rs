@@ -0,0 +1,81 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use transformations::block::{Block, BlockId};
+use transformations::fluid::flow;
+use transformations::world::{IVec3, World};
+
+// the flat world is grass right up to y=127
+const TOP: i32 = 127;
+
+fn ticks(world: &mut World, n: usize) {
+    for _ in 0..n {
+        world.tick();
+    }
+}
+
+#[test]
+fn waterSpreadsThenFallsIntoAHole() {
+    let save = common::tempDir("fluid-spread");
+    let mut world = common::flatWorld(&save);
+    let water = world.registry().id("water").unwrap();
+
+    // a trench along x with a hole under x=4
+    for x in 1..12 {
+        assert!(world.set_block(IVec3::new(x, TOP, 5), BlockId::AIR));
+    }
+    world.set_block(IVec3::new(4, TOP - 1, 5), BlockId::AIR);
+
+    // before any tick, flow only asks for the open side of the source at the trench's end
+    world.set_block(IVec3::new(1, TOP, 5), water);
+    let writes = flow(&world, IVec3::new(1, TOP, 5));
+    let cells: Vec<_> = writes.iter().map(|(p, _)| *p).collect();
+    assert_eq!(cells, vec![IVec3::new(2, TOP, 5)]);
+    assert!(
+        writes
+            .iter()
+            .all(|(_, b)| b.id == water && b.state.level() == 1)
+    );
+
+    ticks(&mut world, 100);
+    for x in 2..=4 {
+        let state = world.get_block_state(IVec3::new(x, TOP, 5));
+        assert_eq!(world.get_block(IVec3::new(x, TOP, 5)), water);
+        assert_eq!(state.level(), (x - 1) as u8);
+    }
+    let fallen = IVec3::new(4, TOP - 1, 5);
+    assert_eq!(world.get_block(fallen), water);
+    assert!(world.get_block_state(fallen).falling());
+    // water pouring into a hole doesn't also spread past it
+    assert_eq!(world.get_block(IVec3::new(5, TOP, 5)), BlockId::AIR);
+    std::fs::remove_dir_all(save).unwrap();
+}
+
+#[test]
+fn lavaTouchingWaterHardens() {
+    let save = common::tempDir("fluid-harden");
+    let mut world = common::flatWorld(&save);
+    let (water, lava, stone) = {
+        let r = world.registry();
+        (
+            r.id("water").unwrap(),
+            r.id("lava").unwrap(),
+            r.id("stone").unwrap(),
+        )
+    };
+
+    for x in 1..4 {
+        world.set_block(IVec3::new(x, TOP, 8), BlockId::AIR);
+    }
+    let at = IVec3::new(2, TOP, 8);
+    world.set_block(IVec3::new(1, TOP, 8), water);
+    world.set_block(at, lava);
+    assert_eq!(flow(&world, at), vec![(at, Block::from(stone))]);
+
+    ticks(&mut world, 40);
+    assert_eq!(world.get_block(at), stone);
+    // the water stays put, it's only the lava that changes
+    assert_eq!(world.get_block(IVec3::new(1, TOP, 8)), water);
+    std::fs::remove_dir_all(save).unwrap();
+}
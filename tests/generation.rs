@@ -0,0 +1,31 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use transformations::world::{IVec3, World};
+
+// every block of the chunks both worlds generated, as (id, state bits)
+fn blocks(world: &World) -> Vec<(u16, u16)> {
+    let mut out = Vec::new();
+    for x in -48..64 {
+        for z in -48..64 {
+            for y in 0..128 {
+                let pos = IVec3::new(x, y, z);
+                out.push((world.get_block(pos).0, world.get_block_state(pos).0));
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn sameSeedSameBlocks() {
+    let save = common::tempDir("generation");
+    let a = blocks(&common::noiseWorld(1337, &save));
+    let b = blocks(&common::noiseWorld(1337, &save));
+    assert!(a == b, "two worlds from the same seed differ");
+
+    let c = blocks(&common::noiseWorld(1338, &save));
+    assert!(a != c, "a different seed gave the same world");
+    std::fs::remove_dir_all(save).unwrap();
+}
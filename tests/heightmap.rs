@@ -0,0 +1,60 @@
+#![allow(non_snake_case)]
+
+use cgmath::Vector3;
+use transformations::block::BlockRegistry;
+use transformations::generator::WorldGenerator;
+use transformations::heightmap::{Heightmap, HeightmapGenerator};
+use transformations::world::{BLOCKDEFS, Chunk, ChunkPos};
+
+#[test]
+fn asciiGridReadsSamplesAndNodata() {
+    let src = "ncols 3\nnrows 2\nxllcorner 100.5\nNODATA_value -9999\n1 2 3\n4 -9999 6\n";
+    let map = Heightmap::parseAsciiGrid(src).unwrap();
+    assert_eq!(map.size(), (3, 2));
+    assert_eq!(map.range(), (1.0, 6.0));
+    assert_eq!(map.sample(2.0, 0.0), Some(3.0));
+    assert_eq!(map.sample(1.0, 1.0), None, "NODATA cell has a height");
+    assert_eq!(map.sample(2.0, 1.0), Some(6.0));
+}
+
+#[test]
+fn asciiGridRejectsBadHeaders() {
+    let err = |src: &str| Heightmap::parseAsciiGrid(src).err().unwrap();
+    assert_eq!(err("nrows 1\n1\n"), "missing ncols");
+    assert_eq!(err("ncols 1\n1\n"), "missing nrows");
+    assert_eq!(err("ncols 1\nnrows 1\nfoo 2\n1\n"), "unknown header 'foo'");
+    assert_eq!(
+        err("ncols x\nnrows 1\n1\n"),
+        "header 'ncols' has bad value 'x'"
+    );
+    assert_eq!(err("ncols 1\nnrows 1\n1 a\n"), "bad sample 'a'");
+}
+
+#[test]
+fn asciiGridChecksSampleCount() {
+    assert_eq!(
+        Heightmap::parseAsciiGrid("ncols 2\nnrows 2\n1 2 3\n").err(),
+        Some("expected 2x2 samples, got 3".to_string())
+    );
+    assert_eq!(
+        Heightmap::parseAsciiGrid("ncols 1\nnrows 2\nNODATA_value 0\n0 0\n").err(),
+        Some("heightmap has no data".to_string())
+    );
+}
+
+#[test]
+fn columnsTallerThanTheChunkKeepTheirGrass() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let map = Heightmap::new(32, 32, vec![0.0; 32 * 32]).unwrap();
+    let mut generator = HeightmapGenerator::new(map, &registry);
+    generator.originX = 0.0;
+    generator.originZ = 0.0;
+    generator.baseHeight = 500.0;
+
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0), 128);
+    generator.terrain(ChunkPos::new(0, 0), &mut chunk);
+    let at = |y| registry.get(chunk.get(Vector3::new(5, y, 5))).name.as_str();
+    assert_eq!(at(127), "grass");
+    assert_eq!(at(126), "dirt");
+    assert_eq!(at(0), "stone");
+}
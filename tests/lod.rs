@@ -0,0 +1,70 @@
+#![allow(non_snake_case)]
+
+use cgmath::Vector3;
+use transformations::block::{BlockId, BlockRegistry};
+use transformations::lod::{LodGrid, LodPick};
+use transformations::mesher::{FaceGrid, Mesher};
+use transformations::world::{BLOCKDEFS, CHUNKSIZE, Chunk, ChunkPos, SectionMesh, VERTEXWORDS};
+
+fn stoneChunk(registry: &BlockRegistry) -> Chunk {
+    let stone = registry.id("stone").unwrap();
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0), 128);
+    for x in 0..CHUNKSIZE {
+        for z in 0..CHUNKSIZE {
+            for y in 0..chunk.height() {
+                chunk.set(Vector3::new(x, y, z), stone);
+            }
+        }
+    }
+    chunk
+}
+
+// quads per face, in DIRS order
+fn faces(mesh: &SectionMesh) -> [usize; 6] {
+    let mut out = [0; 6];
+    for quad in mesh.opaque.vertices.chunks(4 * VERTEXWORDS) {
+        out[((quad[0] >> 27) & 7) as usize] += 1;
+    }
+    out
+}
+
+#[test]
+fn bordersAreCulledAgainstWhatTheNeighbourShows() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let chunk = stoneChunk(&registry);
+    let grid = |chunk: &Chunk, level| LodGrid::build(chunk, &registry, level, LodPick::Surface);
+    let coarse = grid(&chunk, 1);
+    let mut faceGrid = FaceGrid::new();
+
+    // nothing drawn next door, so every edge cell shows its side: 8 cells wide, 8 high
+    let open = coarse.meshSection(
+        0,
+        &[None, None, None, None],
+        &registry,
+        Mesher::Naive,
+        &mut faceGrid,
+    );
+    assert_eq!(faces(&open), [64, 64, 64, 64, 64, 0]);
+
+    // solid neighbours at full detail and at the same level both hide the sides
+    let borders = [
+        Some(grid(&chunk, 0)),
+        Some(grid(&chunk, 0)),
+        Some(grid(&chunk, 1)),
+        Some(grid(&chunk, 1)),
+    ];
+    let closed = coarse.meshSection(0, &borders, &registry, Mesher::Naive, &mut faceGrid);
+    assert_eq!(faces(&closed), [0, 0, 0, 0, 64, 0]);
+
+    // one block dug out of the back neighbour's facing edge opens the 2x2 cell in front of it
+    let mut dug = stoneChunk(&registry);
+    dug.set(Vector3::new(5, 3, CHUNKSIZE - 1), BlockId::AIR);
+    let borders = [
+        Some(grid(&dug, 0)),
+        Some(grid(&chunk, 0)),
+        Some(grid(&chunk, 1)),
+        Some(grid(&chunk, 1)),
+    ];
+    let holed = coarse.meshSection(0, &borders, &registry, Mesher::Naive, &mut faceGrid);
+    assert_eq!(faces(&holed), [1, 0, 0, 0, 64, 0]);
+}
@@ -0,0 +1,84 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use std::collections::HashSet;
+use transformations::block::BlockId;
+use transformations::mesher::Mesher;
+use transformations::world::{ChunkPos, IVec3, MeshUpdate, SUBDIV, VERTEXWORDS, World, unpackPos};
+
+// (chunk, section, face, plane, u, v) for every block face a quad covers
+type UnitFace = (ChunkPos, usize, u32, u32, u32, u32);
+
+// remeshes everything and splits each quad back into the block faces under it
+fn unitFaces(world: &mut World, mesher: Mesher) -> (HashSet<UnitFace>, usize) {
+    world.setMesher(mesher);
+    world.chunkRemeshAll();
+    let (mut faces, mut quads) = (HashSet::new(), 0);
+    for update in world.takeMeshUpdates() {
+        let MeshUpdate::Meshed(pos, sections) = update else {
+            continue;
+        };
+        for (sy, mesh) in sections {
+            for data in [&mesh.opaque, &mesh.cutout, &mesh.translucent] {
+                for quad in data.vertices.chunks(4 * VERTEXWORDS) {
+                    quads += 1;
+                    let face = (quad[0] >> 27) & 7;
+                    let corners: Vec<_> =
+                        quad.chunks(VERTEXWORDS).map(|v| unpackPos(v[0])).collect();
+                    let span = |i: usize| {
+                        let it = corners.iter().map(|c| c[i]);
+                        (it.clone().min().unwrap(), it.max().unwrap())
+                    };
+                    let (min, max): (Vec<u32>, Vec<u32>) = (0..3).map(span).unzip();
+                    // the flat axis is the normal, the other two span the quad
+                    let axis = (0..3).find(|a| min[*a] == max[*a]).unwrap();
+                    let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+                    for u in min[a] / SUBDIV..max[a] / SUBDIV {
+                        for v in min[b] / SUBDIV..max[b] / SUBDIV {
+                            let fresh = faces.insert((pos, sy, face, min[axis], u, v));
+                            assert!(fresh, "{:?} meshed a block face twice", mesher);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    (faces, quads)
+}
+
+#[test]
+fn greedyCoversNaiveFaces() {
+    let save = common::tempDir("mesher");
+    let mut world = common::flatWorld(&save);
+    // a pit, a pillar and a patch of another block so there's more than a flat top
+    let stone = world.registry().id("stone").unwrap();
+    for x in 3..7 {
+        for z in 3..6 {
+            world.set_block(IVec3::new(x, 127, z), BlockId::AIR);
+            world.set_block(IVec3::new(x, 126, z), BlockId::AIR);
+        }
+    }
+    for y in 100..128 {
+        world.set_block(IVec3::new(-5, y, 9), stone);
+    }
+    for x in 10..20 {
+        world.set_block(IVec3::new(x, 127, -3), stone);
+    }
+
+    let (naive, naiveQuads) = unitFaces(&mut world, Mesher::Naive);
+    let (greedy, greedyQuads) = unitFaces(&mut world, Mesher::Greedy);
+    assert_eq!(
+        naive.len(),
+        naiveQuads,
+        "naive quads should be one block face each"
+    );
+    assert!(naive == greedy, "greedy and naive cover different faces");
+    assert!(
+        greedyQuads < naiveQuads,
+        "greedy made {} quads, naive {}",
+        greedyQuads,
+        naiveQuads
+    );
+    std::fs::remove_dir_all(save).unwrap();
+}
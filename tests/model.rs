@@ -0,0 +1,77 @@
+#![allow(non_snake_case)]
+
+use cgmath::Vector3;
+use transformations::block::{BlockRegistry, BlockState};
+use transformations::world::{BLOCKDEFS, MeshData, SUBDIV, VERTEXWORDS, unpackPos};
+
+// (face, lowest corner, highest corner) of each quad baked for `name` at the
+// section origin, corners in sixteenths
+fn bake(
+    registry: &BlockRegistry,
+    name: &str,
+    state: BlockState,
+    hidden: bool,
+) -> Vec<(u32, Vector3<u32>, Vector3<u32>)> {
+    let def = registry.get(registry.id(name).unwrap());
+    let mut mesh = MeshData::default();
+    let model = def.model.as_ref().unwrap();
+    model.bake(def, state, &mut mesh, Vector3::new(0, 0, 0), |_| hidden);
+    mesh.vertices
+        .chunks(4 * VERTEXWORDS)
+        .map(|quad| {
+            let corners: Vec<_> = quad.chunks(VERTEXWORDS).map(|v| unpackPos(v[0])).collect();
+            let min = corners.iter().fold(corners[0], |a, c| {
+                Vector3::new(a.x.min(c.x), a.y.min(c.y), a.z.min(c.z))
+            });
+            let max = corners.iter().fold(corners[0], |a, c| {
+                Vector3::new(a.x.max(c.x), a.y.max(c.y), a.z.max(c.z))
+            });
+            ((quad[0] >> 27) & 7, min, max)
+        })
+        .collect()
+}
+
+#[test]
+fn slabIsHalfABoxAndCullsFlushFaces() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let quads = bake(&registry, "stone_slab", BlockState::default(), false);
+    assert_eq!(quads.len(), 6);
+    assert!(quads.iter().all(|(_, _, max)| max.y <= SUBDIV / 2));
+
+    // covered on every side, only the top sits inside the cell
+    let quads = bake(&registry, "stone_slab", BlockState::default(), true);
+    let top = (5, Vector3::new(0, 8, 0), Vector3::new(16, 8, 16));
+    assert_eq!(quads, vec![top]);
+}
+
+#[test]
+fn stairsTurnWithTheirFacing() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let faces = |facing| {
+        let state = BlockState::default().withFacing(facing);
+        let mut faces: Vec<_> = bake(&registry, "stone_stairs", state, true)
+            .into_iter()
+            .map(|(face, _, _)| face)
+            .collect();
+        faces.sort();
+        faces
+    };
+    // the riser and the lower step's top are all that's left inside the cell
+    assert_eq!(faces(1), vec![1, 5]);
+    // turned around, the riser looks back instead
+    assert_eq!(faces(0), vec![0, 5]);
+    assert_eq!(faces(3), vec![3, 5]);
+}
+
+#[test]
+fn crossIsTwoDiagonalPlanes() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    // neighbours never hide a cross
+    let quads = bake(&registry, "flower", BlockState::default(), true);
+    let faces: Vec<_> = quads.iter().map(|(face, _, _)| *face).collect();
+    assert_eq!(faces, vec![6, 7]);
+    for (_, min, max) in quads {
+        assert_eq!((min.y, max.y), (0, 16));
+        assert_eq!((min.x, max.x, min.z, max.z), (2, 14, 2, 14));
+    }
+}
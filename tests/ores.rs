@@ -0,0 +1,66 @@
+#![allow(non_snake_case)]
+
+use cgmath::Vector3;
+use transformations::block::BlockRegistry;
+use transformations::ores::OreDecorator;
+use transformations::world::{BLOCKDEFS, CHUNKSIZE, Chunk, ChunkPos};
+
+// cells of the ore called `name` after decorating a chunk of solid stone
+fn veins(seed: u64, registry: &BlockRegistry, name: &str) -> Vec<(usize, usize, usize)> {
+    let stone = registry.id("stone").unwrap();
+    let ore = registry.id(name).unwrap();
+    let mut chunk = Chunk::new(ChunkPos::new(2, -1), 128);
+    for x in 0..CHUNKSIZE {
+        for z in 0..CHUNKSIZE {
+            for y in 0..chunk.height() {
+                chunk.set(Vector3::new(x, y, z), stone);
+            }
+        }
+    }
+    OreDecorator::new(seed, registry).decorate(ChunkPos::new(2, -1), &mut chunk);
+
+    let mut out = Vec::new();
+    for x in 0..CHUNKSIZE {
+        for z in 0..CHUNKSIZE {
+            for y in 0..chunk.height() {
+                if chunk.get(Vector3::new(x, y, z)) == ore {
+                    out.push((x, y, z));
+                }
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn sameSeedSameVeins() {
+    let registry = BlockRegistry::load(BLOCKDEFS);
+    let a = veins(7, &registry, "iron_ore");
+    assert!(!a.is_empty(), "no iron in a chunk of stone");
+    assert_eq!(a, veins(7, &registry, "iron_ore"));
+    assert_ne!(a, veins(8, &registry, "iron_ore"));
+}
+
+#[test]
+fn insertingABlockKeepsOtherVeins() {
+    let src = std::fs::read_to_string(BLOCKDEFS).unwrap();
+    // marble only replaces dirt so its own veins can't cover the others in stone
+    let at = src.find("[[block]]\nname = \"coal_ore\"").unwrap();
+    let inserted = format!(
+        "{}[[block]]\nname = \"marble\"\ntextures = {{ all = \"src/textures/txStone.png\" }}\nore = {{ minY = 0, maxY = 64, size = 10, frequency = 4, replaces = [\"dirt\"] }}\n\n{}",
+        &src[..at],
+        &src[at..]
+    );
+
+    let before = BlockRegistry::parse(&src).unwrap();
+    let after = BlockRegistry::parse(&inserted).unwrap();
+    assert_ne!(before.id("iron_ore"), after.id("iron_ore"));
+    for name in ["coal_ore", "iron_ore", "gold_ore"] {
+        assert_eq!(
+            veins(7, &before, name),
+            veins(7, &after, name),
+            "{} moved when a block was inserted before it",
+            name
+        );
+    }
+}
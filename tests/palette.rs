@@ -0,0 +1,38 @@
+#![allow(non_snake_case)]
+
+use transformations::palette::PalettedContainer;
+
+#[test]
+fn getReturnsWhatWasSet() {
+    let mut container = PalettedContainer::new(4096, 0u16);
+    assert!(container.isUniform());
+    // enough distinct values to widen the indices a few times
+    for i in 0..4096 {
+        container.set(i, (i * 7 % 300) as u16);
+    }
+    for i in 0..4096 {
+        assert_eq!(container.get(i), (i * 7 % 300) as u16);
+    }
+    assert_eq!(container.report().bitsPerEntry, 9);
+}
+
+#[test]
+fn compactDropsUnusedValues() {
+    let mut container = PalettedContainer::new(4096, 0u16);
+    for i in 0..4096 {
+        container.set(i, (i % 40) as u16);
+    }
+    for i in 0..4096 {
+        container.set(i, (i % 2) as u16);
+    }
+    container.compact();
+    assert_eq!(container.palette(), &[0, 1]);
+    for i in 0..4096 {
+        assert_eq!(container.get(i), (i % 2) as u16);
+    }
+
+    container.fill(5);
+    container.compact();
+    assert!(container.isUniform());
+    assert_eq!(container.get(4095), 5);
+}
@@ -0,0 +1,76 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use transformations::block::{BlockId, BlockState};
+use transformations::world::IVec3;
+
+#[test]
+fn editedBlockSurvivesReload() {
+    let save = common::tempDir("region");
+    let pos = IVec3::new(-20, 90, 37);
+    let mut world = common::flatWorld(&save);
+    let log = world.registry().id("log").unwrap();
+    let state = BlockState::default().withAxis(BlockState::AXIS_X);
+    assert!(world.set_block_state(pos, log, state));
+    world.saveModified().unwrap();
+
+    let world = common::flatWorld(&save);
+    assert_eq!(world.get_block(pos), log);
+    assert_eq!(world.get_block_state(pos), state);
+    // the rest of the chunk came back too, not just the edit
+    let dirt = world.registry().id("dirt").unwrap();
+    assert_eq!(world.get_block(pos + IVec3::new(1, 0, 0)), dirt);
+    std::fs::remove_dir_all(save).unwrap();
+}
+
+#[test]
+fn freedSectorsAreReused() {
+    let save = common::tempDir("region-sectors");
+    let mut world = common::flatWorld(&save);
+    let stone = world.registry().id("stone").unwrap();
+    let size = || std::fs::metadata(save.join("r.0.0.region")).unwrap().len();
+
+    // two small chunks, one sector each after the header, in that order
+    world.set_block(IVec3::new(1, 120, 1), stone);
+    world.saveModified().unwrap();
+    world.set_block(IVec3::new(17, 120, 1), stone);
+    world.saveModified().unwrap();
+    let small = size();
+
+    // noise doesn't compress, so the first chunk outgrows its sector and moves to the end
+    let mut seed = 12345u32;
+    for x in 0..16 {
+        for y in 64..128 {
+            for z in 0..16 {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let id = 1 + (seed >> 24) as u16 % 6;
+                world.set_block(IVec3::new(x, y, z), BlockId(id));
+            }
+        }
+    }
+    world.saveModified().unwrap();
+    let grown = size();
+    assert!(
+        grown > small + 4096,
+        "the edited chunk should need more than one sector"
+    );
+
+    // a third chunk fits where the first one was
+    world.set_block(IVec3::new(33, 120, 1), stone);
+    world.saveModified().unwrap();
+    assert_eq!(size(), grown);
+    std::fs::remove_dir_all(save).unwrap();
+}
+
+#[test]
+fn chunksBelowLitRefuseEdits() {
+    let save = common::tempDir("region-unlit");
+    let mut world = common::flatWorld(&save);
+    let stone = world.registry().id("stone").unwrap();
+    // the outer ring only gets to Decorated, an edit there couldn't be saved
+    assert!(!world.set_block(IVec3::new(3 * 16 + 1, 120, 0), stone));
+    assert!(world.set_block(IVec3::new(2 * 16 + 1, 120, 0), stone));
+    world.saveModified().unwrap();
+    std::fs::remove_dir_all(save).unwrap();
+}